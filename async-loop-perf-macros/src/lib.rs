@@ -0,0 +1,127 @@
+//! Procedural macros for `async-loop-perf`.
+
+use proc_macro::TokenStream;
+use proc_macro_error2::{emit_warning, proc_macro_error};
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Expr, Ident, Pat, Token, parse_macro_input};
+
+/// Adapters whose output never implements `ExactSizeIterator`, whatever the
+/// input iterator is.
+const NOT_EXACT_SIZE: &[&str] = &[
+    "chain",
+    "char_indices",
+    "chars",
+    "cycle",
+    "filter",
+    "filter_map",
+    "flat_map",
+    "flatten",
+    "lines",
+    "map_while",
+    "scan",
+    "skip_while",
+    "split",
+    "split_whitespace",
+    "take_while",
+];
+
+/// `item in expr, body`
+struct AsyncForEach {
+    pat: Pat,
+    expr: Expr,
+    body: Expr,
+}
+
+impl Parse for AsyncForEach {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let pat = Pat::parse_single(input)?;
+        input.parse::<Token![in]>()?;
+        let expr = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let body = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self { pat, expr, body })
+    }
+}
+
+/// `[]` or `vec![]`: there is nothing to iterate, so nothing is emitted.
+fn is_empty_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Array(array) => array.elems.is_empty(),
+        Expr::Macro(mac) => mac.mac.path.is_ident("vec") && mac.mac.tokens.is_empty(),
+        _ => false,
+    }
+}
+
+/// The outermost adapter of `expr` if it is one that is never exact-size.
+fn not_exact_size_adapter(expr: &Expr) -> Option<&Ident> {
+    match expr {
+        Expr::MethodCall(call) if NOT_EXACT_SIZE.contains(&call.method.to_string().as_str()) => {
+            Some(&call.method)
+        }
+        Expr::Paren(paren) => not_exact_size_adapter(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Expands `async_for_each!(item in expr, body)` into a `for` loop that is
+/// skipped when `expr` yields no items.
+///
+/// The argument is turned into an iterator once and its
+/// `ExactSizeIterator::len()` is checked before the loop starts, which is the
+/// `if !data.is_empty() { for item in data { .. } }` pattern without naming
+/// `expr` twice. A macro only sees tokens, not types, so the choice is made
+/// from the syntax of `expr`:
+///
+/// - `[]` and `vec![]` expand to nothing.
+/// - A call to an adapter that is never exact-size (`filter`, `chain`,
+///   `lines`, ...) expands to a plain `for` loop and emits a warning. Warnings
+///   from `proc_macro_error2` are only shown on a nightly compiler.
+/// - Anything else gets the guard and must implement `ExactSizeIterator`.
+///
+/// ```
+/// # use async_loop_perf_macros::async_for_each;
+/// # futures::executor::block_on(async {
+/// let mut sum = 0;
+/// async_for_each!(x in vec![1, 2, 3], {
+///     sum += std::future::ready(x).await;
+/// });
+/// assert_eq!(sum, 6);
+/// # });
+/// ```
+#[proc_macro]
+#[proc_macro_error]
+pub fn async_for_each(input: TokenStream) -> TokenStream {
+    let AsyncForEach { pat, expr, body } = parse_macro_input!(input as AsyncForEach);
+
+    if is_empty_literal(&expr) {
+        return quote!({}).into();
+    }
+
+    if let Some(adapter) = not_exact_size_adapter(&expr) {
+        emit_warning!(
+            adapter,
+            "`async_for_each!`: `{}` is never an ExactSizeIterator; emitting an unguarded loop",
+            adapter
+        );
+        return quote!({
+            for #pat in #expr {
+                #body
+            }
+        })
+        .into();
+    }
+
+    let iter = Ident::new("iter", Span::mixed_site());
+    quote!({
+        let #iter = ::core::iter::IntoIterator::into_iter(#expr);
+        if ::core::iter::ExactSizeIterator::len(&#iter) != 0 {
+            for #pat in #iter {
+                #body
+            }
+        }
+    })
+    .into()
+}
@@ -0,0 +1,84 @@
+use async_loop_perf_macros::async_for_each;
+use futures::executor::block_on;
+use std::cell::Cell;
+
+async fn record(seen: &Cell<Vec<i32>>, item: i32) {
+    let mut items = seen.take();
+    items.push(item);
+    seen.set(items);
+}
+
+#[test]
+fn non_empty_input_runs_every_item_in_order() {
+    let seen = Cell::new(Vec::new());
+    block_on(async {
+        async_for_each!(item in vec![3, 1, 2], {
+            record(&seen, item).await;
+        });
+    });
+    assert_eq!(seen.take(), [3, 1, 2]);
+}
+
+#[test]
+fn empty_input_skips_the_body() {
+    let data: Vec<i32> = Vec::new();
+    let calls = Cell::new(0);
+    block_on(async {
+        async_for_each!(_item in &data, {
+            calls.set(calls.get() + 1);
+        });
+    });
+    assert_eq!(calls.get(), 0);
+}
+
+#[test]
+fn expression_is_evaluated_once() {
+    let evaluated = Cell::new(0);
+    let data = || {
+        evaluated.set(evaluated.get() + 1);
+        vec![1, 2]
+    };
+    let sum = Cell::new(0);
+    block_on(async {
+        async_for_each!(item in data(), {
+            sum.set(sum.get() + std::future::ready(item).await);
+        });
+    });
+    assert_eq!(evaluated.get(), 1);
+    assert_eq!(sum.get(), 3);
+}
+
+#[test]
+fn empty_literals_expand_to_nothing() {
+    let calls = Cell::new(0);
+    async_for_each!(_item in [], {
+        calls.set(calls.get() + 1);
+    });
+    async_for_each!(_item in vec![], {
+        calls.set(calls.get() + 1);
+    });
+    assert_eq!(calls.get(), 0);
+}
+
+#[test]
+fn non_exact_size_adapter_falls_back_to_a_plain_loop() {
+    let sum = Cell::new(0);
+    block_on(async {
+        async_for_each!(item in (1..=6).filter(|n| n % 2 == 0), {
+            sum.set(sum.get() + std::future::ready(item).await);
+        });
+    });
+    assert_eq!(sum.get(), 12);
+}
+
+#[test]
+fn patterns_destructure_items() {
+    let pairs = [(1, 10), (2, 20)];
+    let sum = Cell::new(0);
+    block_on(async {
+        async_for_each!((a, b) in pairs, {
+            sum.set(sum.get() + a * b);
+        });
+    });
+    assert_eq!(sum.get(), 50);
+}
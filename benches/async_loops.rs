@@ -1,5 +1,79 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+#[cfg(feature = "alloc-tracking")]
+use async_loop_perf::alloc_tracking::with_alloc_count;
+use async_loop_perf::async_actor::{Actor, Handler, Message as ActorMessage};
+use async_loop_perf::async_cleanup::{AsyncCleanup, CleanupRegistry};
+use async_loop_perf::async_consensus::{Data, Node, QuorumError, quorum_write};
+use async_loop_perf::async_cooperative_multitasking::{CoopScheduler, run_cooperatively};
+use async_loop_perf::async_cooperative_ownership as ownership;
+use async_loop_perf::async_dag_executor::{Dag, Task, execute_dag};
+use async_loop_perf::async_drain_ordering::{drain_in_order, iter_in_order};
+use async_loop_perf::async_epoch::{EpochGuard, reclaim_in_epoch};
+use async_loop_perf::async_event_bus::{AsyncEventBus, AsyncSubscriber, Event};
+use async_loop_perf::async_histogram::update_histogram;
+use async_loop_perf::async_lsm_tree::{SsTable, compact_table, maybe_compact};
+use async_loop_perf::async_prefetch_buffer::PrefetchBuffer;
+use async_loop_perf::async_rollup::{TimeseriesEvent, rollup_window};
+use async_loop_perf::async_routing::{AsyncHandler, AsyncRouter, Message};
+use async_loop_perf::async_schema_migration::{
+    AsyncDb, Migration, MigrationError, run_pending_migrations,
+};
+use async_loop_perf::async_segment_tree::{RangeQuery, SegmentTree, process_range_queries};
+use async_loop_perf::async_sharded_processor::ShardedProcessor;
+use async_loop_perf::async_split_processing::{
+    Categorizable, FastHandler, SlowHandler, split_process,
+};
+use async_loop_perf::async_state_sync::{Delta, Replica, State, SyncResult, sync_state};
+use async_loop_perf::async_token_bucket::{AsyncTokenSource, DistributedTokenBucket};
+use async_loop_perf::async_two_phase_commit::{Participant, TwoPhaseError, two_phase_commit};
+use async_loop_perf::async_version_vector::{
+    Node as VersionVectorNode, SyncResult as VectorSyncResult, VectorClock, sync_vector_clock,
+};
+use async_loop_perf::async_work_list::process_work_list;
+use async_loop_perf::async_work_stealing_deque::WorkStealingDeque;
+use async_loop_perf::combinators::seq_and_then;
+use async_loop_perf::custom_waker::StackWaker;
+use async_loop_perf::intrusive_vec::IntrusiveVec;
+use async_loop_perf::mir_demo::{
+    async_fn_loop, async_work, deque_no_check, deque_with_check, hash_map_no_check,
+    hash_map_with_check, no_check, no_check_array, no_check_result, no_check_slice, poll_fn_loop,
+    recursive_async_loop, recursive_async_loop_no_check, with_check, with_check_array,
+    with_check_result, with_check_slice,
+};
+use async_loop_perf::waker_bench::{CountingWaker, WakerCounts, noop_waker};
+use criterion::measurement::{Measurement, ValueFormatter, WallTime};
+use criterion::profiler::Profiler;
+use criterion::{
+    BatchSize, BenchmarkGroup, BenchmarkId, Criterion, Throughput, black_box, criterion_group,
+    criterion_main,
+};
+use futures::FutureExt;
+use futures::future::{BoxFuture, join_all, pending, poll_immediate};
+use futures::stream::{FuturesOrdered, FuturesUnordered, StreamExt};
+use hdrhistogram::Histogram;
+use http_body_util::{BodyExt, Empty, Full};
+use hyper::body::Bytes;
+use hyper_util::rt::TokioIo;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::Infallible;
+use std::future::Future;
+use std::io::{self, IoSlice};
+use std::mem;
+use std::ops::Range;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
+use tokio::sync::{Mutex, Notify, RwLock, broadcast, mpsc, oneshot};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 
 /*
 ================================================================================
@@ -175,5 +249,6578 @@ fn bench_empty_check(c: &mut Criterion) {
     group_io.finish();
 }
 
-criterion_group!(benches, bench_empty_check);
-criterion_main!(benches);
+// ============================================================
+// Scenario 116: Latency percentiles - Tail latency instead of mean
+// Criterion only reports mean/median and stddev. For production services the
+// tail (p99.9) matters more, so every iteration is timed individually and
+// recorded into an HDR histogram.
+// ============================================================
+
+/// Runs `iters` iterations of `f`, recording each iteration's latency (ns)
+/// into `hist`, and returns the total elapsed time for Criterion.
+async fn record_latencies<F, Fut>(hist: &mut Histogram<u64>, iters: u64, f: F) -> Duration
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let mut total = Duration::ZERO;
+    for _ in 0..iters {
+        let start = Instant::now();
+        f().await;
+        let elapsed = start.elapsed();
+        hist.saturating_record(elapsed.as_nanos() as u64);
+        total += elapsed;
+    }
+    total
+}
+
+fn print_percentiles(name: &str, hist: &Histogram<u64>) {
+    println!(
+        "{name:<22} p50={:>6}ns  p95={:>6}ns  p99={:>6}ns  p999={:>6}ns  max={:>8}ns  (n={})",
+        hist.value_at_quantile(0.50),
+        hist.value_at_quantile(0.95),
+        hist.value_at_quantile(0.99),
+        hist.value_at_quantile(0.999),
+        hist.max(),
+        hist.len(),
+    );
+}
+
+fn bench_async_overhead_percentiles(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let empty_data: Vec<i32> = vec![];
+    let one_item: Vec<i32> = vec![1];
+
+    let mut group = c.benchmark_group("116. latency percentiles");
+
+    // NOTE: each sample includes one `Instant::now()` pair (~20ns), so for the
+    // empty cases the absolute percentiles are dominated by timer overhead.
+    // Compare the *shape* of the tail between cases, not the raw numbers.
+    let cases: [(&str, &Vec<i32>, bool); 4] = [
+        ("with_check (empty)", &empty_data, true),
+        ("no_check (empty)", &empty_data, false),
+        ("with_check (1 item)", &one_item, true),
+        ("no_check (1 item)", &one_item, false),
+    ];
+
+    let mut results = Vec::new();
+    for (name, input, check) in cases {
+        let mut hist = Histogram::<u64>::new_with_bounds(1, 60_000_000_000, 3).unwrap();
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                rt.block_on(record_latencies(&mut hist, iters, || async {
+                    let data = black_box(input);
+                    if !check || !data.is_empty() {
+                        async_loop_with_await(data.clone()).await;
+                    }
+                }))
+            })
+        });
+        results.push((name, hist));
+    }
+
+    group.finish();
+
+    // Filtered-out cases never ran and have nothing to report.
+    for (name, hist) in results.iter().filter(|(_, hist)| !hist.is_empty()) {
+        print_percentiles(name, hist);
+    }
+}
+
+// ============================================================
+// async_consensus: quorum_write over [0, 3, 5, 9] nodes
+// With no peers the guard returns before building the per-node futures and
+// the `FuturesUnordered` set, so the 0-node case should sit near the
+// with_check numbers from Scenario 1. `quorum_write_no_check` builds them
+// anyway, and with 0 nodes it returns `NotReached` (1 ack required, none
+// possible), so its result is only black-boxed. Nodes and payload come
+// from the batch setup, so no case times their allocation; dropping them
+// is still timed.
+// Measured: 0 nodes ~95 ns guarded vs ~263 ns unguarded; from 3 nodes up
+// the two are level (~1 µs at 3, ~2.5 µs at 9).
+// ============================================================
+
+/// An in-process node that acknowledges after one scheduler round-trip.
+struct LocalNode;
+
+impl Node for LocalNode {
+    async fn write(&self, data: &Data) -> bool {
+        tokio::task::yield_now().await;
+        black_box(data);
+        true
+    }
+}
+
+async fn quorum_write_no_check(nodes: Vec<LocalNode>, data: Data) -> Result<(), QuorumError> {
+    let required = nodes.len() / 2 + 1;
+    let mut pending: FuturesUnordered<_> = nodes.iter().map(|node| node.write(&data)).collect();
+
+    let mut acks = 0;
+    while let Some(acked) = pending.next().await {
+        if acked {
+            acks += 1;
+            if acks >= required {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(QuorumError::NotReached { acks, required })
+}
+
+fn bench_async_consensus(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let setup = |n: usize| {
+        (
+            (0..n).map(|_| LocalNode).collect::<Vec<_>>(),
+            Data(vec![1, 2, 3]),
+        )
+    };
+    let mut group = c.benchmark_group("async_consensus: quorum_write");
+
+    for n in [0usize, 3, 5, 9] {
+        group.bench_with_input(BenchmarkId::new("with_check", n), &n, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || setup(n),
+                |(nodes, data)| async move { quorum_write(nodes, data).await.unwrap() },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", n), &n, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || setup(n),
+                |(nodes, data)| async move { black_box(quorum_write_no_check(nodes, data).await) },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 14: Mutex-protected loop - Lock scope vs empty-check
+// Real services often keep a `Mutex<Vec<T>>` and drain it asynchronously.
+// The guard can only run after the lock is acquired, so the lock cost is paid
+// either way; what differs is how long the lock is held.
+//
+// Drain-then-iterate (clone under the lock, iterate after releasing it) is
+// almost always preferable: other tasks can push while we await, and a
+// `tokio::sync::Mutex` guard held across `.await` serialises every producer
+// behind the slowest item. Holding the lock across the loop is only correct
+// when items must not be observed by anyone else until processing finishes.
+//
+// Each round runs the loop over [0, 16, 256] items next to a contender that
+// takes the lock 8 times to push (and pop, so the size stays put), yielding
+// in between; the timing covers both. The contender's mean wait per lock is
+// printed before the group runs. With the guard held across the loop, the
+// contender can only get the lock once the whole loop is done; with
+// drain-then-iterate, it gets it right after the clone.
+//
+// Measured: at 256 items a hold-lock round takes ~19-23 µs vs ~10-12 µs for
+// drain-then-iterate, and the contender waits ~2.7 µs per lock vs ~110 ns
+// (~340 ns vs ~110 ns at 16 items). At 0 items both are ~1.5-1.9 µs, and the
+// guard saves ~0.2 µs either way.
+// ============================================================
+
+/// Drain-then-iterate: the lock is held only long enough to clone the vec.
+async fn async_loop_with_mutex<const CHECK: bool>(shared: Arc<Mutex<Vec<i32>>>) {
+    let data = {
+        let guard = shared.lock().await;
+        if CHECK && guard.is_empty() {
+            return;
+        }
+        guard.clone()
+    };
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+/// Hold-lock-across-iteration: the guard lives until the loop finishes.
+async fn async_loop_holding_mutex<const CHECK: bool>(shared: Arc<Mutex<Vec<i32>>>) {
+    let guard = shared.lock().await;
+    if CHECK && guard.is_empty() {
+        return;
+    }
+    for item in guard.iter() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+/// Lock acquisitions the contender makes in each round.
+const CONTENDER_PUSHES: usize = 8;
+
+/// Runs one mutex loop next to a contender that keeps taking the lock to
+/// push (and pop again, so the vec keeps its size), returning how long the
+/// contender waited for the lock in total.
+async fn contended_mutex_round<const HOLD: bool, const CHECK: bool>(
+    shared: Arc<Mutex<Vec<i32>>>,
+) -> Duration {
+    let contender = async {
+        let mut waited = Duration::ZERO;
+        for _ in 0..CONTENDER_PUSHES {
+            let start = Instant::now();
+            let mut guard = shared.lock().await;
+            waited += start.elapsed();
+            guard.push(-1);
+            guard.pop();
+            drop(guard);
+            tokio::task::yield_now().await;
+        }
+        waited
+    };
+    let consumer = async {
+        if HOLD {
+            async_loop_holding_mutex::<CHECK>(shared.clone()).await;
+        } else {
+            async_loop_with_mutex::<CHECK>(shared.clone()).await;
+        }
+    };
+    // `join!` polls the consumer first, so it always takes the lock first.
+    let ((), waited) = futures::join!(consumer, contender);
+    waited
+}
+
+fn round_fn(
+    variant: &str,
+    check: bool,
+) -> fn(Arc<Mutex<Vec<i32>>>) -> BoxFuture<'static, Duration> {
+    match (variant, check) {
+        ("hold-lock", true) => |s| Box::pin(contended_mutex_round::<true, true>(s)),
+        ("hold-lock", false) => |s| Box::pin(contended_mutex_round::<true, false>(s)),
+        (_, true) => |s| Box::pin(contended_mutex_round::<false, true>(s)),
+        (_, false) => |s| Box::pin(contended_mutex_round::<false, false>(s)),
+    }
+}
+
+fn bench_mutex_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let sizes = [0, 16, 256];
+    let variants = ["drain-then-iterate", "hold-lock"];
+
+    for n in sizes {
+        let shared = Arc::new(Mutex::new((0..n).collect::<Vec<i32>>()));
+        for variant in variants {
+            let round = round_fn(variant, true);
+            let rounds = 100;
+            let waited: Duration = (0..rounds)
+                .map(|_| rt.block_on(round(shared.clone())))
+                .sum();
+            println!(
+                "{n} items, {variant}: contender waits {:?} per lock",
+                waited / (rounds * CONTENDER_PUSHES as u32)
+            );
+        }
+    }
+
+    let mut group = c.benchmark_group("14. Mutex-protected loop");
+
+    for n in sizes {
+        let shared = Arc::new(Mutex::new((0..n).collect::<Vec<i32>>()));
+        for variant in variants {
+            for (check, label) in [(true, "with_check"), (false, "no_check")] {
+                let round = round_fn(variant, check);
+                group.bench_with_input(
+                    BenchmarkId::new(format!("{variant}/{label}"), n),
+                    &shared,
+                    |b, shared| b.to_async(&rt).iter(|| round(black_box(shared.clone()))),
+                );
+            }
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 15: RwLock vs Mutex - Choosing the synchronisation primitive
+// Readers that only iterate can share a `RwLock` read guard. When processed
+// items must be removed, a write guard is needed; checking `is_empty()` under
+// a read guard first avoids taking the exclusive lock for nothing.
+//
+// Read-lock promotion pitfall: `tokio::sync::RwLock` has no upgrade. Calling
+// `write().await` while the same task still holds a read guard waits for that
+// guard to be released, i.e. forever. Always drop the read guard (as the
+// `{ ... }` scope in `async_loop_rwlock_drain` does) before asking to write.
+// ============================================================
+
+/// Iterates under a shared read guard.
+async fn async_loop_rwlock<const CHECK: bool>(shared: Arc<RwLock<Vec<i32>>>) {
+    let guard = shared.read().await;
+    if CHECK && guard.is_empty() {
+        return;
+    }
+    for item in guard.iter() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+/// Removes processed items, so it needs a write guard. With `CHECK` the
+/// emptiness test happens under a read guard and the write lock is skipped.
+async fn async_loop_rwlock_drain<const CHECK: bool>(shared: Arc<RwLock<Vec<i32>>>) {
+    if CHECK && shared.read().await.is_empty() {
+        return;
+    }
+    let data = std::mem::take(&mut *shared.write().await);
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_rwlock_vs_mutex(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mutex = Arc::new(Mutex::new(Vec::<i32>::new()));
+    let rwlock = Arc::new(RwLock::new(Vec::<i32>::new()));
+
+    let mut group = c.benchmark_group("15. RwLock vs Mutex");
+
+    group.bench_function("mutex/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_with_mutex::<true>(black_box(mutex.clone())))
+    });
+
+    group.bench_function("mutex/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_with_mutex::<false>(black_box(mutex.clone())))
+    });
+
+    group.bench_function("rwlock read/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_rwlock::<true>(black_box(rwlock.clone())))
+    });
+
+    group.bench_function("rwlock read/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_rwlock::<false>(black_box(rwlock.clone())))
+    });
+
+    group.bench_function("rwlock write/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_rwlock_drain::<true>(black_box(rwlock.clone())))
+    });
+
+    group.bench_function("rwlock write/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| async_loop_rwlock_drain::<false>(black_box(rwlock.clone())))
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Intrusive list vs Vec - Where the elements live
+// For empty input both collections are equally cheap to guard. The difference
+// shows up when the collection is built per call: `Vec` allocates as soon as
+// it holds one element, while `IntrusiveVec` keeps its nodes on the stack.
+// See `src/intrusive_vec.rs` for the MIR of both empty paths.
+// ============================================================
+
+const INTRUSIVE_ITEMS: i32 = 8;
+
+/// Async loop over any borrowed collection, one suspension per item.
+async fn async_loop_borrowed<'a, C>(data: &'a C)
+where
+    &'a C: IntoIterator<Item = &'a i32>,
+{
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_async_intrusive_list(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("Intrusive list vs Vec");
+
+    group.bench_function("intrusive (empty)/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let list = black_box(IntrusiveVec::<i32, 8>::new());
+            if !list.is_empty() {
+                async_loop_borrowed(&list).await;
+            }
+        })
+    });
+
+    group.bench_function("intrusive (empty)/no_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let list = black_box(IntrusiveVec::<i32, 8>::new());
+            async_loop_borrowed(&list).await;
+        })
+    });
+
+    group.bench_function("vec (empty)/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(Vec::<i32>::new());
+            if !data.is_empty() {
+                async_loop_borrowed(&data).await;
+            }
+        })
+    });
+
+    group.bench_function("vec (empty)/no_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(Vec::<i32>::new());
+            async_loop_borrowed(&data).await;
+        })
+    });
+
+    // Non-empty: the collection is built inside the iteration, so the Vec
+    // case includes one heap allocation and the intrusive case none.
+    group.bench_function("intrusive (8 items)/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let mut list = IntrusiveVec::<i32, 8>::new();
+            for i in 0..black_box(INTRUSIVE_ITEMS) {
+                list.push(i).unwrap();
+            }
+            if !list.is_empty() {
+                async_loop_borrowed(&list).await;
+            }
+        })
+    });
+
+    group.bench_function("vec (8 items)/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data: Vec<i32> = (0..black_box(INTRUSIVE_ITEMS)).collect();
+            if !data.is_empty() {
+                async_loop_borrowed(&data).await;
+            }
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// async_routing: route with [0, 1, 5] handlers per route, [5, 50] routes
+// The HashMap lookup is paid by every message; the guard then skips the
+// handler loop for routes with no handlers and for unknown routes.
+// `route_no_check` does the same lookup and loops over whatever it found.
+// Messages borrow a static key, so no message allocates in the timed path.
+// ============================================================
+
+struct RoutedMessage(&'static str);
+
+impl Message for RoutedMessage {
+    fn route_key(&self) -> &str {
+        self.0
+    }
+}
+
+async fn route_no_check(router: &AsyncRouter<RoutedMessage>, msg: RoutedMessage) {
+    for handler in router.handlers(msg.route_key()).into_iter().flatten() {
+        handler.handle(&msg).await;
+    }
+}
+
+struct YieldHandler;
+
+impl AsyncHandler<RoutedMessage> for YieldHandler {
+    fn handle<'a>(&'a self, msg: &'a RoutedMessage) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            black_box(msg);
+        })
+    }
+}
+
+fn bench_async_routing(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_routing: route");
+
+    for routes in [5usize, 50] {
+        for handlers in [0usize, 1, 5] {
+            let mut router = AsyncRouter::new();
+            for r in 0..routes {
+                router.add_route(format!("route-{r}"));
+                for _ in 0..handlers {
+                    router.register(format!("route-{r}"), Box::new(YieldHandler));
+                }
+            }
+
+            let mut keys = vec![("known route", "route-0")];
+            if handlers == 0 {
+                keys.push(("unknown route", "missing"));
+            }
+            for (target, key) in keys {
+                let id = format!("{target}/routes={routes}/handlers={handlers}");
+                group.bench_function(BenchmarkId::new("with_check", &id), |b| {
+                    b.to_async(&rt)
+                        .iter(|| router.route(black_box(RoutedMessage(key))))
+                });
+                group.bench_function(BenchmarkId::new("no_check", &id), |b| {
+                    b.to_async(&rt)
+                        .iter(|| route_no_check(&router, black_box(RoutedMessage(key))))
+                });
+            }
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 16: Oneshot collection - Collecting loop output
+// `join_all` over an empty iterator still builds an empty `Vec` of futures and
+// polls the combinator once; the oneshot variant with a guard skips
+// everything, including both `Vec::with_capacity` calls.
+// ============================================================
+
+/// Collects one result per item through a dedicated `oneshot` channel.
+///
+/// More flexible than `join_all`: each `Sender` can be moved to another task
+/// or stored and completed later by a callback, while the caller only keeps
+/// the receivers and awaits them in input order.
+async fn async_loop_oneshot_collect(data: Vec<i32>) -> Vec<i32> {
+    let mut receivers = Vec::with_capacity(data.len());
+    for item in data {
+        let (tx, rx) = oneshot::channel();
+        receivers.push(rx);
+        tokio::task::yield_now().await;
+        let _ = tx.send(black_box(item) * 2);
+    }
+
+    let mut results = Vec::with_capacity(receivers.len());
+    for rx in receivers {
+        results.push(rx.await.unwrap());
+    }
+    results
+}
+
+/// Collects results with `join_all`, which owns every per-item future.
+async fn async_loop_join_all_collect(data: Vec<i32>) -> Vec<i32> {
+    join_all(data.into_iter().map(|item| async move {
+        tokio::task::yield_now().await;
+        black_box(item) * 2
+    }))
+    .await
+}
+
+fn bench_oneshot_collection(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let empty_data: Vec<i32> = vec![];
+
+    let mut group = c.benchmark_group("16. Oneshot collection");
+
+    group.bench_function("oneshot/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            if !data.is_empty() {
+                black_box(async_loop_oneshot_collect(data.clone()).await);
+            }
+        })
+    });
+
+    group.bench_function("oneshot/no_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            black_box(async_loop_oneshot_collect(data.clone()).await);
+        })
+    });
+
+    group.bench_function("join_all/with_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            if !data.is_empty() {
+                black_box(async_loop_join_all_collect(data.clone()).await);
+            }
+        })
+    });
+
+    group.bench_function("join_all/no_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            black_box(async_loop_join_all_collect(data.clone()).await);
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 118: Generational cleanup - GC-inspired ephemeral async state
+// The young generation is small and usually empty but checked every tick;
+// the old generation is large and only swept once it passes a threshold.
+// Each iteration runs GC_TICKS ticks, and the young generation receives new
+// items once every `n` ticks, so larger `n` means more empty young-gen checks.
+// ============================================================
+
+const GC_TICKS: usize = 100;
+const GC_OLD_THRESHOLD: usize = 64;
+
+/// Sweeps the young generation, promoting every survivor to the old one.
+async fn gc_young(young: &mut Vec<i32>, old: &mut Vec<i32>) {
+    for item in young.drain(..) {
+        tokio::task::yield_now().await;
+        old.push(black_box(item));
+    }
+}
+
+/// Sweeps the old generation.
+async fn gc_old(old: &mut Vec<i32>) {
+    for item in old.drain(..) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn run_generational_ticks<const CHECK: bool>(every: usize) {
+    let mut young = Vec::with_capacity(4);
+    let mut old = Vec::with_capacity(GC_OLD_THRESHOLD + 4);
+
+    for tick in 0..GC_TICKS {
+        if tick % every == 0 {
+            young.extend_from_slice(&[1, 2, 3, 4]);
+        }
+        if !CHECK || !young.is_empty() {
+            gc_young(&mut young, &mut old).await;
+        }
+        if old.len() > GC_OLD_THRESHOLD {
+            gc_old(&mut old).await;
+        }
+    }
+}
+
+fn bench_async_generational_gc(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("118. generational cleanup");
+
+    for every in [1usize, 10, 100] {
+        let id = format!("young filled every {every} ticks");
+        group.bench_with_input(BenchmarkId::new("with_check", &id), &every, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| run_generational_ticks::<true>(black_box(n)))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", &id), &every, |b, &n| {
+            b.to_async(&rt)
+                .iter(|| run_generational_ticks::<false>(black_box(n)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 17: Result-returning loops - Cost of `?` in the state machine
+// Each `?` adds a `Try::branch` call and a discriminant switch per iteration
+// (see Cases C/D in `src/mir_demo.rs`). Two inputs: empty data, where the
+// guard skips the loop entirely, and data whose first item fails, where both
+// variants pay for one iteration plus the `Err(String)` construction.
+// ============================================================
+
+fn bench_result_loops(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let empty_data: Vec<i32> = vec![];
+    let failing_data: Vec<i32> = vec![-1, 2, 3];
+
+    let mut group = c.benchmark_group("17. Result-returning loops");
+
+    group.bench_function("with_check (empty)", |b| {
+        b.to_async(&rt)
+            .iter(|| async { with_check_result(black_box(&empty_data)).await.unwrap() })
+    });
+
+    group.bench_function("no_check (empty)", |b| {
+        b.to_async(&rt)
+            .iter(|| async { no_check_result(black_box(&empty_data)).await.unwrap() })
+    });
+
+    group.bench_function("with_check (error on first item)", |b| {
+        b.to_async(&rt).iter(|| async {
+            black_box(
+                with_check_result(black_box(&failing_data))
+                    .await
+                    .unwrap_err(),
+            )
+        })
+    });
+
+    group.bench_function("no_check (error on first item)", |b| {
+        b.to_async(&rt).iter(|| async {
+            black_box(no_check_result(black_box(&failing_data)).await.unwrap_err())
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// async_work_stealing_deque: lockless vs locked empty-check
+// An idle worker checks both halves of its deque on every scheduling pass.
+// The local check is a length read; the stolen check must lock first, so the
+// empty fast path is expected to be roughly an order of magnitude slower.
+// ============================================================
+
+async fn process_deque_item(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+fn bench_work_stealing_deque(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_work_stealing_deque: empty check");
+
+    // `process_local` needs `&mut self`, which cannot escape an `iter` closure,
+    // so both cases drive their own loop with `iter_custom`.
+    let mut deque = WorkStealingDeque::<i32>::new();
+    group.bench_function("local (lockless)", |b| {
+        b.iter_custom(|iters| {
+            rt.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    black_box(&mut deque)
+                        .process_local(process_deque_item)
+                        .await;
+                }
+                start.elapsed()
+            })
+        })
+    });
+
+    let deque = WorkStealingDeque::<i32>::new();
+    group.bench_function("stolen (locked)", |b| {
+        b.iter_custom(|iters| {
+            rt.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    black_box(&deque).process_stolen(process_deque_item).await;
+                }
+                start.elapsed()
+            })
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 119: Loop fusion - Two passes over the data fused into one
+// Unfused code pays iterator setup (and, with guards, one check) per loop;
+// the fused loop needs a single guard. For empty data the unfused no_check
+// version builds two iterators that each immediately return `None`.
+// ============================================================
+
+async fn preprocess(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+async fn postprocess(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+async fn unfused_loops<const CHECK: bool>(data: &[i32]) {
+    if !CHECK || !data.is_empty() {
+        for &item in data {
+            preprocess(item).await;
+        }
+    }
+    if !CHECK || !data.is_empty() {
+        for &item in data {
+            postprocess(item).await;
+        }
+    }
+}
+
+async fn fused_loop<const CHECK: bool>(data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        preprocess(item).await;
+        postprocess(item).await;
+    }
+}
+
+fn bench_async_loop_fusion(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("119. loop fusion");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("unfused/with_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| unfused_loops::<true>(black_box(d))),
+        );
+        group.bench_with_input(BenchmarkId::new("unfused/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| unfused_loops::<false>(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("fused/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| fused_loop::<true>(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("fused/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| fused_loop::<false>(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_sharded_processor: N = [4, 16, 64] shards, three load shapes
+//   all-empty:     no shard has work
+//   one-shard-hot: N items, all in shard 0
+//   uniform:       one item per shard
+// `no_check` drains every shard without the per-shard guard, paying a
+// `drain(..)` iterator for each empty shard.
+// ============================================================
+
+async fn process_shard_item(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+fn sharded_input<const N: usize>(load: &str) -> ShardedProcessor<i32, N> {
+    let mut processor = ShardedProcessor::new();
+    let shards = processor.shards_mut();
+    match load {
+        "all-empty" => {}
+        "one-shard-hot" => shards[0].extend(0..N as i32),
+        "uniform" => {
+            for (i, shard) in shards.iter_mut().enumerate() {
+                shard.push(i as i32);
+            }
+        }
+        _ => unreachable!("unknown load shape {load}"),
+    }
+    processor
+}
+
+fn bench_shards<const N: usize>(group: &mut BenchmarkGroup<'_, WallTime>, rt: &Runtime) {
+    for load in ["all-empty", "one-shard-hot", "uniform"] {
+        let id = format!("N={N}/{load}");
+        group.bench_function(BenchmarkId::new("with_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || sharded_input::<N>(load),
+                |mut processor| async move {
+                    processor.process_all_shards(process_shard_item).await;
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || sharded_input::<N>(load),
+                |mut processor| async move {
+                    for shard in processor.shards_mut() {
+                        for item in shard.drain(..) {
+                            process_shard_item(item).await;
+                        }
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn bench_sharded_processor(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_sharded_processor: process_all_shards");
+
+    bench_shards::<4>(&mut group, &rt);
+    bench_shards::<16>(&mut group, &rt);
+    bench_shards::<64>(&mut group, &rt);
+
+    group.finish();
+}
+
+// ============================================================
+// async_trait overhead - Box<dyn Future> per call vs static dispatch
+// Native `async fn` in traits (stable since 1.75) returns an anonymous
+// `impl Future`; called on a concrete type it is statically dispatched and
+// can be inlined. The `async-trait` macro rewrites the method to return
+// `Pin<Box<dyn Future + Send>>`, which is what makes the trait object-safe:
+// every call goes through the vtable and allocates a box for its future.
+// That per-item allocation makes skipping the loop for empty input more
+// valuable, though for empty input neither variant makes a call at all.
+// ============================================================
+
+trait AsyncProcessor {
+    async fn process(&self, item: i32);
+}
+
+#[async_trait::async_trait]
+trait BoxedAsyncProcessor {
+    async fn process(&self, item: i32);
+}
+
+struct YieldProcessor;
+
+impl AsyncProcessor for YieldProcessor {
+    async fn process(&self, item: i32) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+#[async_trait::async_trait]
+impl BoxedAsyncProcessor for YieldProcessor {
+    async fn process(&self, item: i32) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn static_dispatch_loop<P: AsyncProcessor>(processor: &P, data: &[i32]) {
+    for &item in data {
+        processor.process(item).await;
+    }
+}
+
+async fn dyn_dispatch_loop(processor: &dyn BoxedAsyncProcessor, data: &[i32]) {
+    for &item in data {
+        processor.process(item).await;
+    }
+}
+
+fn bench_async_trait_overhead(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let processor = YieldProcessor;
+    let dyn_processor: &dyn BoxedAsyncProcessor = &processor;
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("async_trait overhead");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("static/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        static_dispatch_loop(&processor, data).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("static/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| static_dispatch_loop(&processor, black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("async-trait/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        dyn_dispatch_loop(dyn_processor, data).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("async-trait/no_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| dyn_dispatch_loop(dyn_processor, black_box(d)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 18: Dynamic vs static dispatch (inner future)
+// Boxing the per-item future costs one allocation plus a vtable call per
+// iteration; in a tight loop that dominates the ~0.5ns the empty-check saves.
+// The state-machine sizes are printed once for reference: boxing only shrinks
+// the outer future when the inner one is larger than a fat pointer, which the
+// 1-byte `async_work` future is not.
+// ============================================================
+
+/// Awaits `async_work()` inline; the awaitee is stored in the state machine.
+async fn loop_static_inner(data: Vec<i32>) {
+    for item in data {
+        async_work().await;
+        black_box(item);
+    }
+}
+
+/// Boxes each per-item future behind `dyn Future`.
+async fn loop_boxed_inner(data: Vec<i32>) {
+    for item in data {
+        let fut: Pin<Box<dyn Future<Output = ()>>> = Box::pin(async_work());
+        fut.await;
+        black_box(item);
+    }
+}
+
+fn bench_inner_future_dispatch(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("16 items", (0..16).collect())];
+
+    println!(
+        "state machine sizes: loop_static_inner = {} bytes, loop_boxed_inner = {} bytes",
+        mem::size_of_val(&loop_static_inner(Vec::new())),
+        mem::size_of_val(&loop_boxed_inner(Vec::new())),
+    );
+
+    let mut group = c.benchmark_group("18. Dynamic vs static dispatch (inner future)");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("static/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        loop_static_inner(data.clone()).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("static/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                loop_static_inner(data.clone()).await;
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("boxed/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    loop_boxed_inner(data.clone()).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("boxed/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                loop_boxed_inner(data.clone()).await;
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 120: zero-copy I/O - Scatter-gather writes with IoSlice
+// The loop only borrows each item's bytes into an `IoSlice` list, and one
+// `write_vectored` call hands them all to the kernel without copying into an
+// intermediate buffer. For empty data the guard skips that call, which
+// otherwise still costs a `writev` syscall with zero buffers.
+// Partial writes are not retried: the benchmark measures one call per batch
+// and only reports how many bytes the kernel accepted.
+// ============================================================
+
+async fn write_scatter_gather<const CHECK: bool>(
+    stream: &mut TcpStream,
+    data: &[String],
+) -> io::Result<usize> {
+    let mut slices = Vec::with_capacity(data.len());
+    for item in data {
+        slices.push(IoSlice::new(item.as_bytes()));
+    }
+    if !CHECK || !slices.is_empty() {
+        return stream.write_vectored(&slices).await;
+    }
+    Ok(0)
+}
+
+/// Connects a loopback TCP pair and spawns a task that discards what it reads.
+async fn loopback_stream() -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await
+        .unwrap();
+    let (mut server, _) = listener.accept().await.unwrap();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 64 * 1024];
+        while matches!(server.read(&mut buf).await, Ok(n) if n > 0) {}
+    });
+    client
+}
+
+fn bench_async_zero_copy(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut stream = rt.block_on(loopback_stream());
+    let inputs: [(&str, Vec<String>); 2] = [
+        ("empty", vec![]),
+        ("4 items", (0..4).map(|i| format!("item-{i}")).collect()),
+    ];
+
+    let mut group = c.benchmark_group("120. zero-copy I/O");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("with_check", label), data, |b, d| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        black_box(
+                            write_scatter_gather::<true>(&mut stream, black_box(d))
+                                .await
+                                .unwrap(),
+                        );
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        black_box(
+                            write_scatter_gather::<false>(&mut stream, black_box(d))
+                                .await
+                                .unwrap(),
+                        );
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_event_bus: publish with [0, 1, 10] subscribers, [5, 50] event types
+// `Topic<K>` gives every K its own `TypeId`. "subscribed type" publishes
+// `Topic<0>`, one of the registered types; "unknown type" publishes one that
+// never was, which the bus's lock-free filter rejects before taking the
+// `RwLock` read guard. With 0 subscribers nothing is registered at all.
+// ============================================================
+
+#[derive(Clone, Copy)]
+struct Topic<const K: usize>;
+
+impl<const K: usize> Event for Topic<K> {}
+
+/// The bus-wide event type; subscribers only see which topic fired.
+struct TopicEvent(usize);
+
+impl Event for TopicEvent {}
+
+impl<const K: usize> From<Topic<K>> for TopicEvent {
+    fn from(_: Topic<K>) -> Self {
+        TopicEvent(K)
+    }
+}
+
+struct YieldSubscriber;
+
+impl AsyncSubscriber<TopicEvent> for YieldSubscriber {
+    fn on_event<'a>(&'a self, event: &'a TopicEvent) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            black_box(event.0);
+        })
+    }
+}
+
+fn subscribe_topic<const K: usize>(bus: &mut AsyncEventBus<TopicEvent>, subscribers: usize) {
+    for _ in 0..subscribers {
+        bus.subscribe::<Topic<K>>(Box::new(YieldSubscriber));
+    }
+}
+
+macro_rules! topic_subscribers {
+    ($($k:literal),*) => {
+        [$(subscribe_topic::<$k> as fn(&mut AsyncEventBus<TopicEvent>, usize)),*]
+    };
+}
+
+fn bench_async_event_bus(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let registrars = topic_subscribers!(
+        0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+        25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40, 41, 42, 43, 44, 45, 46, 47,
+        48, 49
+    );
+
+    let mut group = c.benchmark_group("async_event_bus: publish");
+
+    for types in [5usize, 50] {
+        for subscribers in [0usize, 1, 10] {
+            let mut bus = AsyncEventBus::new();
+            for register in &registrars[..types] {
+                register(&mut bus, subscribers);
+            }
+
+            let id = format!("types={types}/subscribers={subscribers}");
+            group.bench_function(BenchmarkId::new("subscribed type", &id), |b| {
+                b.to_async(&rt).iter(|| bus.publish(black_box(Topic::<0>)))
+            });
+            group.bench_function(BenchmarkId::new("unknown type", &id), |b| {
+                b.to_async(&rt).iter(|| bus.publish(black_box(Topic::<99>)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Runtime scaling - Worker-thread count vs the empty-check delta
+// Scenarios 1 and 2 rerun on multi-thread runtimes with 1, 2, 4 and 8
+// workers. `block_on` itself runs on the bench thread, but the runtime's
+// per-poll bookkeeping (cooperative budget, waker wiring into a scheduler
+// whose idle workers park and must be notified when work is stolen or
+// injected) grows with the worker count. The hypothesis is that this makes
+// every poll of an empty loop slightly more expensive on large machines,
+// so the guard, which skips that poll, is worth more there.
+// ============================================================
+
+fn bench_runtime_scaling(c: &mut Criterion) {
+    let empty_data: Vec<i32> = vec![];
+    let one_item: Vec<i32> = vec![1];
+
+    for workers in [1usize, 2, 4, 8] {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(workers)
+            .enable_all()
+            .build()
+            .unwrap();
+
+        let mut group = c.benchmark_group(format!("Runtime scaling (worker_threads={workers})"));
+        // Scenario 2 sleeps for real, so keep the sample count down.
+        group.sample_size(10);
+
+        group.bench_function("1. yield_now/with_check (empty)", |b| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(&empty_data);
+                if !data.is_empty() {
+                    async_loop_with_await(data.clone()).await;
+                }
+            })
+        });
+
+        group.bench_function("1. yield_now/no_check (empty)", |b| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(&empty_data);
+                async_loop_with_await(data.clone()).await;
+            })
+        });
+
+        group.bench_function("2. sleep/with_check (1 item)", |b| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(&one_item);
+                if !data.is_empty() {
+                    async_loop_with_sleep(data.clone()).await;
+                }
+            })
+        });
+
+        group.bench_function("2. sleep/no_check (1 item)", |b| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(&one_item);
+                async_loop_with_sleep(data.clone()).await;
+            })
+        });
+
+        group.finish();
+    }
+}
+
+// ============================================================
+// bench_async_state_sync: [0, 3, 9] replicas x [1, 100, 10000]-entry deltas
+// Every replica starts at version 0, so the delta it receives is the whole
+// state. `delta_since` runs once per replica, so with 0 replicas neither
+// version builds a delta: that row is flat across delta sizes while the
+// others grow with it. `sync_state_no_check` drops the guard to show what it
+// is worth on that row.
+//
+// Measured: 0 replicas cost ~13-17 ns with or without the guard at every
+// delta size; 3 and 9 replicas grow from ~0.16-0.57 µs (1 entry) to
+// ~60-250 µs (10000 entries), with run-to-run noise larger than any
+// guard difference.
+// ============================================================
+
+struct InProcessReplica;
+
+impl Replica for InProcessReplica {
+    fn acked_version(&self) -> u64 {
+        0
+    }
+
+    async fn apply(&self, delta: &Delta) -> bool {
+        tokio::task::yield_now().await;
+        black_box(delta.len());
+        true
+    }
+}
+
+async fn sync_state_no_check<R: Replica>(replicas: Vec<R>, local_state: &State) -> SyncResult {
+    let mut result = SyncResult::default();
+    for replica in &replicas {
+        let delta = local_state.delta_since(replica.acked_version());
+        if replica.apply(&delta).await {
+            result.synced += 1;
+        } else {
+            result.failed += 1;
+        }
+    }
+    result
+}
+
+fn bench_async_state_sync(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_state_sync: sync_state");
+
+    for delta_size in [1u64, 100, 10_000] {
+        let mut state = State::default();
+        for key in 0..delta_size {
+            state.put(key, key as i64);
+        }
+
+        for replicas in [0usize, 3, 9] {
+            let params = format!("replicas={replicas}/delta={delta_size}");
+            group.bench_function(BenchmarkId::new("with_check", &params), |b| {
+                b.to_async(&rt).iter(|| async {
+                    let replicas: Vec<InProcessReplica> =
+                        (0..replicas).map(|_| InProcessReplica).collect();
+                    black_box(sync_state(black_box(replicas), &state).await)
+                })
+            });
+            group.bench_function(BenchmarkId::new("no_check", &params), |b| {
+                b.to_async(&rt).iter(|| async {
+                    let replicas: Vec<InProcessReplica> =
+                        (0..replicas).map(|_| InProcessReplica).collect();
+                    black_box(sync_state_no_check(black_box(replicas), &state).await)
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 19: Slice vs Vec - Borrowed parameter types
+// `&[i32]` and `&Vec<i32>` generate the same empty-path basic blocks (see
+// Cases E/F in `src/mir_demo.rs`), so the with/without-check gap should be
+// the same for both. Taking an owned `Vec` would add a clone on top, which
+// is what Scenario 1 pays via `data.clone()`.
+// ============================================================
+
+fn bench_slice_vs_vec(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let empty_data: Vec<i32> = vec![];
+
+    let mut group = c.benchmark_group("19. Slice vs Vec");
+
+    group.bench_function("vec/with_check", |b| {
+        b.to_async(&rt).iter(|| with_check(black_box(&empty_data)))
+    });
+
+    group.bench_function("vec/no_check", |b| {
+        b.to_async(&rt).iter(|| no_check(black_box(&empty_data)))
+    });
+
+    group.bench_function("slice/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| with_check_slice(black_box(empty_data.as_slice())))
+    });
+
+    group.bench_function("slice/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| no_check_slice(black_box(empty_data.as_slice())))
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// async_cooperative_multitasking: budgets [1, 10, 100, unlimited], 64 items
+// A smaller budget yields more often. The empty cases compare
+// `run_cooperatively` (guard before the scheduler exists) with a loop that
+// always constructs a `CoopScheduler` first.
+// ============================================================
+
+async fn coop_work(item: i32) {
+    black_box(item);
+}
+
+fn bench_cooperative_multitasking(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let items: Vec<i32> = (0..64).collect();
+    let empty_data: Vec<i32> = vec![];
+
+    let mut group = c.benchmark_group("async_cooperative_multitasking: budget");
+
+    for (label, budget) in [
+        ("1", 1u32),
+        ("10", 10),
+        ("100", 100),
+        ("unlimited", u32::MAX),
+    ] {
+        group.bench_with_input(
+            BenchmarkId::new("64 items", label),
+            &budget,
+            |b, &budget| {
+                b.to_async(&rt)
+                    .iter(|| run_cooperatively(black_box(items.clone()), budget, coop_work))
+            },
+        );
+    }
+
+    group.bench_function("empty/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| run_cooperatively(black_box(empty_data.clone()), 10, coop_work))
+    });
+
+    group.bench_function("empty/no_check", |b| {
+        b.to_async(&rt).iter(|| async {
+            let scheduler = black_box(CoopScheduler::new(10));
+            for item in black_box(empty_data.clone()) {
+                scheduler.yield_if_needed().await;
+                coop_work(item).await;
+            }
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 20: Channel drain - Producer/consumer loop exit cost
+// `while let Some(item) = rx.recv().await` on a closed-and-empty channel
+// still creates and polls one `recv()` future to learn that it is done. A
+// `try_recv()` probe answers that synchronously and skips the future.
+//
+// recv-after-close: closing (dropping every `Sender`) does not discard
+// buffered messages; `recv()` keeps returning them and yields `None` only
+// once the buffer is empty. `try_recv()` mirrors this with `Ok(item)`, then
+// `Err(Disconnected)`, so the guard never loses pre-filled items.
+// ============================================================
+
+async fn process_channel_item(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+async fn channel_drain_loop(mut rx: mpsc::Receiver<i32>) {
+    while let Some(item) = rx.recv().await {
+        process_channel_item(item).await;
+    }
+}
+
+async fn channel_drain_loop_with_check(mut rx: mpsc::Receiver<i32>) {
+    match rx.try_recv() {
+        Ok(item) => process_channel_item(item).await,
+        Err(mpsc::error::TryRecvError::Disconnected) => return,
+        Err(mpsc::error::TryRecvError::Empty) => {}
+    }
+    channel_drain_loop(rx).await;
+}
+
+/// A receiver whose channel holds `prefill` items and has no senders left.
+fn closed_channel(prefill: i32) -> mpsc::Receiver<i32> {
+    let (tx, rx) = mpsc::channel(16);
+    for item in 0..prefill {
+        tx.try_send(item).unwrap();
+    }
+    rx
+}
+
+fn bench_channel_drain(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("20. Channel drain");
+
+    for (label, prefill) in [("closed-and-empty", 0), ("pre-filled (8 items)", 8)] {
+        group.bench_function(BenchmarkId::new("with_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_channel(prefill),
+                channel_drain_loop_with_check,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_channel(prefill),
+                channel_drain_loop,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 21: spawn_blocking loop - Bridging sync work into async
+// Each item is handed to tokio's blocking pool, which costs a task
+// allocation, a queue push, a thread wake-up and a JoinHandle await, even
+// though `sync_work` itself returns instantly. For empty input no item
+// reaches the pool either way, so the guard only saves the usual iterator
+// setup; the pool cost shows up in the per-item cases.
+//
+// Thread-pool prewarming: blocking threads are spawned lazily, so the very
+// first `spawn_blocking` also pays for creating an OS thread. Criterion's
+// warm-up phase absorbs that, but a cold service sees it on first request.
+// ============================================================
+
+fn sync_work(item: i32) -> i32 {
+    black_box(item) * 2
+}
+
+async fn async_loop_spawn_blocking(data: Vec<i32>) {
+    for item in data {
+        let result = tokio::task::spawn_blocking(move || sync_work(item))
+            .await
+            .unwrap();
+        black_box(result);
+    }
+}
+
+fn bench_spawn_blocking_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("1 item", vec![1])];
+
+    let mut group = c.benchmark_group("21. spawn_blocking loop");
+    group.sample_size(20); // Thread hand-offs make each sample slow and noisy
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    async_loop_spawn_blocking(data.clone()).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                async_loop_spawn_blocking(data.clone()).await;
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 121: compiler optimization levels
+// The MIR in `src/mir_demo.rs` is pre-optimisation; LLVM may remove more or
+// less of the iterator setup depending on opt-level. A single binary has one
+// opt-level, so this group is rerun under each `bench-opt-*` profile from
+// Cargo.toml and labels its results with the level `build.rs` recorded:
+//
+//   for level in 0 1 2 3 s z; do
+//       cargo bench --profile bench-opt-$level --bench async_loops -- 121
+//   done
+//
+// All runs land in the same Criterion report, one line per level, so the
+// level where no_check reaches with_check (if any) is easy to spot.
+// ============================================================
+
+const OPT_LEVEL: &str = env!("ASYNC_LOOP_PERF_OPT_LEVEL");
+
+fn bench_async_multiversion(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let empty_data: Vec<i32> = vec![];
+    let level = format!("opt-level={OPT_LEVEL}");
+
+    let mut group = c.benchmark_group("121. compiler optimization levels");
+
+    group.bench_function(BenchmarkId::new("with_check", &level), |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            if !data.is_empty() {
+                async_loop_with_await(data.clone()).await;
+            }
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("no_check", &level), |b| {
+        b.to_async(&rt).iter(|| async {
+            let data = black_box(&empty_data);
+            async_loop_with_await(data.clone()).await;
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// async_cooperative_ownership: owned vs & vs &mut loops (empty data)
+// The owned variants take a fresh empty `Vec` each iteration (no allocation),
+// the borrowed ones a reference to a long-lived one. See the module docs for
+// the MIR and state-machine size of each mode.
+// ============================================================
+
+fn bench_cooperative_ownership(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_cooperative_ownership: empty data");
+
+    group.bench_function("owned/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| ownership::owned_with_check(black_box(Vec::new())))
+    });
+
+    group.bench_function("owned/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| ownership::owned_no_check(black_box(Vec::new())))
+    });
+
+    let data: Vec<i32> = Vec::new();
+    group.bench_function("borrowed/with_check", |b| {
+        b.to_async(&rt)
+            .iter(|| ownership::borrowed_with_check(black_box(&data)))
+    });
+
+    group.bench_function("borrowed/no_check", |b| {
+        b.to_async(&rt)
+            .iter(|| ownership::borrowed_no_check(black_box(&data)))
+    });
+
+    // `&mut` cannot escape an `iter` closure, so drive the loop directly.
+    let mut data: Vec<i32> = Vec::new();
+    group.bench_function("mut_borrowed/with_check", |b| {
+        b.iter_custom(|iters| {
+            rt.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    ownership::mut_borrowed_with_check(black_box(&mut data)).await;
+                }
+                start.elapsed()
+            })
+        })
+    });
+
+    group.bench_function("mut_borrowed/no_check", |b| {
+        b.iter_custom(|iters| {
+            rt.block_on(async {
+                let start = Instant::now();
+                for _ in 0..iters {
+                    ownership::mut_borrowed_no_check(black_box(&mut data)).await;
+                }
+                start.elapsed()
+            })
+        })
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 22: Recursive async loop - Box::pin per level
+// Recursing through `Box::pin` allocates once per level; the base-case
+// empty-check skips boxing the final call that would find no items (Cases
+// G/H in `src/mir_demo.rs`). Depth = number of items = recursion depth.
+//
+// Run with `--features alloc-tracking` to also print the allocator calls per
+// depth, which should differ by exactly one between the two variants.
+// ============================================================
+
+fn bench_recursive_async_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let depths = [0usize, 1, 5, 10];
+
+    #[cfg(feature = "alloc-tracking")]
+    for depth in depths {
+        let data: Vec<i32> = (0..depth as i32).collect();
+        let (_, with_check, _) = {
+            let data = data.clone();
+            with_alloc_count(|| rt.block_on(recursive_async_loop(data)))
+        };
+        let (_, no_check, _) =
+            with_alloc_count(|| rt.block_on(recursive_async_loop_no_check(data)));
+        println!(
+            "recursive depth {depth:>2}: with_check {with_check} allocs, no_check {no_check} allocs"
+        );
+    }
+
+    let mut group = c.benchmark_group("22. Recursive async loop");
+
+    for depth in depths {
+        let data: Vec<i32> = (0..depth as i32).collect();
+        group.bench_with_input(BenchmarkId::new("with_check", depth), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| recursive_async_loop(black_box(d.clone())))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", depth), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| recursive_async_loop_no_check(black_box(d.clone())))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Pipeline stages: parse -> validate -> transform -> persist
+// Each stage is its own async loop that awaits once per item and hands a
+// fresh Vec to the next stage. `stages=k` runs only the first k stages, so
+// the step between k-1 and k is that stage's share of the overhead.
+//   no_guard:    every stage future is built and polled, even for no items
+//   first_guard: one `is_empty()` at the pipeline entry skips all four stages
+//   every_stage: each stage checks its own input, but the stage futures are
+//                still constructed and polled one after another
+// ============================================================
+
+async fn pipeline_stage<const CHECK: bool>(data: Vec<i32>, f: fn(i32) -> Option<i32>) -> Vec<i32> {
+    if CHECK && data.is_empty() {
+        return data;
+    }
+    let mut out = Vec::with_capacity(data.len());
+    for item in data {
+        tokio::task::yield_now().await;
+        out.extend(f(item));
+    }
+    out
+}
+
+async fn parse_stage<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    pipeline_stage::<CHECK>(data, |item| Some(black_box(item))).await
+}
+
+async fn validate_stage<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    pipeline_stage::<CHECK>(data, |item| (item >= 0).then_some(item)).await
+}
+
+async fn transform_stage<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    pipeline_stage::<CHECK>(data, |item| Some(item.wrapping_mul(2))).await
+}
+
+async fn persist_stage<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    pipeline_stage::<CHECK>(data, |item| {
+        black_box(item);
+        None
+    })
+    .await
+}
+
+async fn run_pipeline<const GUARD_FIRST: bool, const GUARD_EACH: bool>(
+    data: Vec<i32>,
+    stages: usize,
+) -> usize {
+    if GUARD_FIRST && data.is_empty() {
+        return 0;
+    }
+    let data = parse_stage::<GUARD_EACH>(data).await;
+    if stages == 1 {
+        return data.len();
+    }
+    let data = validate_stage::<GUARD_EACH>(data).await;
+    if stages == 2 {
+        return data.len();
+    }
+    let data = transform_stage::<GUARD_EACH>(data).await;
+    if stages == 3 {
+        return data.len();
+    }
+    persist_stage::<GUARD_EACH>(data).await.len()
+}
+
+fn bench_async_pipeline_stages(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("122. pipeline stages");
+
+    for (label, data) in &inputs {
+        for stages in 1..=4 {
+            let id = format!("no_guard/stages={stages}");
+            group.bench_with_input(BenchmarkId::new(id, label), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| run_pipeline::<false, false>(black_box(d.clone()), stages))
+            });
+        }
+        group.bench_with_input(BenchmarkId::new("first_guard", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| run_pipeline::<true, false>(black_box(d.clone()), 4))
+        });
+        group.bench_with_input(BenchmarkId::new("every_stage", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| run_pipeline::<false, true>(black_box(d.clone()), 4))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 23: Broadcast drain - Multi-consumer loop exit cost
+// Every `broadcast::Receiver` sees every message, so each consumer runs its
+// own `recv()` loop. As with mpsc (Scenario 20), a closed-and-empty channel
+// still costs one `recv()` future before `Err(Closed)` ends the loop; a
+// `try_recv()` probe learns the same thing synchronously.
+//
+// `Err(Lagged(n))` means the receiver fell behind and `n` messages were
+// overwritten; the loop skips ahead and keeps draining what is left.
+// ============================================================
+
+async fn broadcast_drain_loop(mut rx: broadcast::Receiver<i32>) {
+    loop {
+        match rx.recv().await {
+            Ok(item) => process_channel_item(item).await,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn broadcast_drain_loop_with_check(mut rx: broadcast::Receiver<i32>) {
+    match rx.try_recv() {
+        Ok(item) => process_channel_item(item).await,
+        Err(broadcast::error::TryRecvError::Closed) => return,
+        Err(broadcast::error::TryRecvError::Empty | broadcast::error::TryRecvError::Lagged(_)) => {}
+    }
+    broadcast_drain_loop(rx).await;
+}
+
+/// A receiver that was subscribed before `prefill` sends and whose sender is gone.
+fn closed_broadcast(prefill: i32) -> broadcast::Receiver<i32> {
+    let (tx, rx) = broadcast::channel(16);
+    for item in 0..prefill {
+        tx.send(item).unwrap();
+    }
+    rx
+}
+
+fn bench_broadcast_drain(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("23. Broadcast drain");
+
+    for (label, prefill) in [("closed-and-empty", 0), ("pre-seeded (10 messages)", 10)] {
+        group.bench_function(BenchmarkId::new("with_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_broadcast(prefill),
+                broadcast_drain_loop_with_check,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_broadcast(prefill),
+                broadcast_drain_loop,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_actor: process_mailbox with [0, 1, 10, 100] queued messages
+// An actor woken with an empty mailbox returns after one length check;
+// `no_check` runs `process_mailbox_no_check`, the same pass with the same
+// bookkeeping but without that guard.
+// ============================================================
+
+struct Ping(i32);
+
+impl ActorMessage for Ping {}
+
+struct PingHandler;
+
+impl Handler<Ping> for PingHandler {
+    async fn handle(&self, msg: Ping) {
+        tokio::task::yield_now().await;
+        black_box(msg.0);
+    }
+}
+
+fn actor_with_mailbox(messages: i32) -> Actor<Ping> {
+    let mut actor = Actor::new();
+    for i in 0..messages {
+        actor.send(Ping(i));
+    }
+    actor
+}
+
+fn bench_async_actor(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_actor: process_mailbox");
+
+    for messages in [0, 1, 10, 100] {
+        group.bench_function(BenchmarkId::new("with_check", messages), |b| {
+            b.to_async(&rt).iter_batched(
+                || actor_with_mailbox(messages),
+                |mut actor| async move { actor.process_mailbox(&PingHandler).await },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", messages), |b| {
+            b.to_async(&rt).iter_batched(
+                || actor_with_mailbox(messages),
+                |mut actor| async move { actor.process_mailbox_no_check(&PingHandler).await },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 24: AtomicBool vs is_empty check - Signal-driven loops
+// Some loops are gated on a flag set by another task (`should_run`) rather
+// than on the data itself. Here the flag mirrors the data: it is set only
+// when there is work, so both guards skip the same loops.
+//
+// Memory ordering: the flag only says "go"; the data is handed over by value,
+// so nothing else has to become visible with it and `Relaxed` is enough. Use
+// `Acquire` (paired with a `Release` store) when the flag publishes data
+// written elsewhere. On x86-64 and AArch64 both compile to a plain load with
+// no fence; the difference `is_empty()` avoids is the `Arc` dereference and
+// that the compiler may not cache or merge an atomic load, not a barrier.
+// ============================================================
+
+async fn loop_with_atomic_guard(flag: Arc<AtomicBool>, data: Vec<i32>) {
+    if !flag.load(Ordering::Relaxed) {
+        return;
+    }
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn loop_with_is_empty_guard(data: Vec<i32>) {
+    if data.is_empty() {
+        return;
+    }
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_atomic_vs_is_empty(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("24. AtomicBool vs is_empty check");
+
+    for (label, data) in &inputs {
+        let flag = Arc::new(AtomicBool::new(!data.is_empty()));
+        // Clone the Arc and the Vec in setup so neither variant times a
+        // refcount bump or allocation.
+        group.bench_with_input(BenchmarkId::new("atomic_guard", label), data, |b, d| {
+            b.to_async(&rt).iter_batched(
+                || (flag.clone(), d.clone()),
+                |(flag, d)| loop_with_atomic_guard(flag, d),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("is_empty_guard", label), data, |b, d| {
+            b.to_async(&rt).iter_batched(
+                || d.clone(),
+                loop_with_is_empty_guard,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_drain_ordering: drain(..) vs iter() for order-sensitive processing
+// Each event is folded into an order-dependent running hash, as when
+// replaying an event log. Both loops are guarded; the buffer is cloned in
+// setup so `iter` is not credited with avoiding the copy `drain` consumes.
+// ============================================================
+
+async fn apply_event(state: &Cell<i64>, event: i32) {
+    tokio::task::yield_now().await;
+    state.set(state.get().wrapping_mul(31).wrapping_add(i64::from(event)));
+}
+
+fn bench_async_drain_ordering(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("64 events", (0..64).collect())];
+
+    let mut group = c.benchmark_group("async_drain_ordering: ordered replay");
+
+    for (label, events) in &inputs {
+        group.bench_with_input(BenchmarkId::new("drain", label), events, |b, e| {
+            b.to_async(&rt).iter_batched(
+                || e.clone(),
+                |mut events| async move {
+                    let state = Cell::new(0i64);
+                    drain_in_order::<true, _, _, _>(&mut events, |event| {
+                        apply_event(&state, event)
+                    })
+                    .await;
+                    black_box(state.get())
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("iter", label), events, |b, e| {
+            b.to_async(&rt).iter_batched(
+                || e.clone(),
+                |events| async move {
+                    let state = Cell::new(0i64);
+                    iter_in_order::<true, _, _, _>(&events, |event| apply_event(&state, *event))
+                        .await;
+                    black_box(state.get())
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 25: Iterator adapter chains - Check the source, not the adapter
+// `data.iter().filter(..)` is a `Filter<Iter<i32>>`: it cannot know how many
+// items will pass without running the predicate, so it has no `len()` or
+// `is_empty()`. The source `Vec` still does, and an empty source means an
+// empty chain, so `data.is_empty()` before building the adapter skips the
+// loop just as well. The check is conservative: a non-empty source whose
+// items are all filtered out still enters the loop.
+// `filter+map/no_check` is the shape users write when the adapter is built
+// first and there is nothing left to call `is_empty()` on.
+// ============================================================
+
+async fn raw_loop<const CHECK: bool>(data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn filter_loop<const CHECK: bool>(data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data.iter().filter(|&&x| x > 0) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn filter_map_loop(data: &[i32]) {
+    for item in data.iter().filter(|&&x| x > 0).map(|&x| x * 2) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_iterator_adapter_chains(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("25. Iterator adapter chains");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("raw/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| raw_loop::<true>(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("raw/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| raw_loop::<false>(black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("filter/with_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| filter_loop::<true>(black_box(d))),
+        );
+        group.bench_with_input(BenchmarkId::new("filter/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| filter_loop::<false>(black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("filter+map/no_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| filter_map_loop(black_box(d))),
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 26: HashMap loop - Map-based async loops
+// `HashMap` has no `ExactSizeIterator` to ask, but `HashMap::is_empty()` is
+// just as cheap as on `Vec`. Skipping the loop avoids building a hashbrown
+// raw iterator, which loads the first control group even for an empty map
+// (Cases I/J in `src/mir_demo.rs`).
+// ============================================================
+
+fn bench_hash_map_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, HashMap<i32, i32>); 2] = [
+        ("empty", HashMap::new()),
+        ("8 entries", (0..8).map(|i| (i, i)).collect()),
+    ];
+
+    let mut group = c.benchmark_group("26. HashMap loop");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| hash_map_with_check(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| hash_map_no_check(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 123: async vs alloc isolation - Splitting the Box::pin cost
+// `loop_boxed_inner` (Scenario 18) pays for two things per item: a heap
+// allocation and a `dyn Future` poll through a vtable. Storing each future
+// in a slot of an arena allocated up front keeps the `dyn` poll but drops
+// the allocation, so:
+//   boxed - arena  = allocation cost
+//   arena - inline = dynamic dispatch cost
+//   inline         = the state machine itself
+// The arena is built in the batch setup and is not timed.
+// ============================================================
+
+/// One pinned slot per item, allocated before the loop runs.
+type FutureArena<F> = Vec<Pin<Box<Option<F>>>>;
+
+fn future_arena<F>(len: usize) -> FutureArena<F> {
+    (0..len).map(|_| Box::pin(None)).collect()
+}
+
+/// Places each per-item future in its arena slot and polls it as `dyn Future`.
+async fn loop_arena_inner<F>(data: Vec<i32>, arena: &mut FutureArena<F>, make: fn() -> F)
+where
+    F: Future<Output = ()>,
+{
+    for (item, slot) in data.into_iter().zip(arena.iter_mut()) {
+        slot.set(Some(make()));
+        let fut: Pin<&mut dyn Future<Output = ()>> = slot.as_mut().as_pin_mut().unwrap();
+        fut.await;
+        black_box(item);
+    }
+}
+
+fn bench_async_vs_alloc_isolation(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let data: Vec<i32> = (0..16).collect();
+
+    let mut group = c.benchmark_group("123. async vs alloc isolation");
+    group.throughput(Throughput::Elements(data.len() as u64));
+
+    group.bench_function("inline", |b| {
+        b.to_async(&rt)
+            .iter_batched(|| data.clone(), loop_static_inner, BatchSize::SmallInput)
+    });
+    group.bench_function("arena (dyn, no alloc)", |b| {
+        b.to_async(&rt).iter_batched(
+            || (data.clone(), future_arena(data.len())),
+            |(data, mut arena)| async move {
+                loop_arena_inner(data, &mut arena, async_work).await;
+                arena
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("Box::pin (dyn + alloc)", |b| {
+        b.to_async(&rt)
+            .iter_batched(|| data.clone(), loop_boxed_inner, BatchSize::SmallInput)
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 27: VecDeque loop - Ring-buffer work queues
+// Iterating a `VecDeque` first splits it into its two contiguous slices
+// (Cases K/L in `src/mir_demo.rs`), so creating the iterator costs a little
+// more than for a Vec. "wrapped" has its items split across the end of the
+// buffer; "contiguous" has them in one run.
+// ============================================================
+
+async fn async_loop_deque(data: VecDeque<i32>) {
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn wrapped_deque(len: i32) -> VecDeque<i32> {
+    let mut deque = VecDeque::with_capacity(len as usize);
+    deque.extend(0..len / 2);
+    for item in len / 2..len {
+        deque.push_front(item);
+    }
+    deque
+}
+
+fn bench_vec_deque_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, VecDeque<i32>); 3] = [
+        ("empty", VecDeque::new()),
+        ("contiguous (8 items)", (0..8).collect()),
+        ("wrapped (8 items)", wrapped_deque(8)),
+    ];
+
+    let mut group = c.benchmark_group("27. VecDeque loop");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("owned/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter_batched(
+                || d.clone(),
+                |data| async move {
+                    if !data.is_empty() {
+                        async_loop_deque(data).await;
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("owned/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter_batched(|| d.clone(), async_loop_deque, BatchSize::SmallInput)
+        });
+        group.bench_with_input(
+            BenchmarkId::new("borrowed/with_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| deque_with_check(black_box(d))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("borrowed/no_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| deque_no_check(black_box(d))),
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_cleanup: run_all with [0, 1, 10] registered cleanups
+// Each cleanup yields once, standing in for a flush or close. The registry
+// is rebuilt in setup, so only the cleanup pass is timed.
+// ============================================================
+
+struct FlushCleanup(i32);
+
+impl AsyncCleanup for FlushCleanup {
+    fn cleanup(&mut self) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            black_box(self.0);
+        })
+    }
+}
+
+fn cleanup_registry(cleanups: i32) -> CleanupRegistry {
+    let mut registry = CleanupRegistry::new();
+    for i in 0..cleanups {
+        registry.register(Box::new(FlushCleanup(i)));
+    }
+    registry
+}
+
+fn bench_async_cleanup(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_cleanup: run_all");
+
+    for cleanups in [0, 1, 10] {
+        group.bench_function(BenchmarkId::from_parameter(cleanups), |b| {
+            b.to_async(&rt).iter_batched(
+                || cleanup_registry(cleanups),
+                |mut registry| async move { registry.run_all().await },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 124: parallel vs sequential N=2 - When does join_all pay off?
+// Each item's work is a wait of the given duration on tokio's blocking pool
+// (`thread::sleep`, standing in for a blocking call such as a syscall), so
+// two items overlap on separate threads even on a single core. `join_all`
+// polls both futures in one task and must track which of them woke it;
+// awaiting them back to back does not. With no work that bookkeeping is
+// measured against one extra pool round-trip; once the wait dominates,
+// overlapping the two waits approaches a 2x win. Note that the OS rounds
+// short sleeps up (timer slack is ~50µs on Linux), so "1µs" waits far longer.
+// Both variants return early for empty input.
+// ============================================================
+
+async fn timed_work(item: i32, duration: Duration) {
+    tokio::task::spawn_blocking(move || std::thread::sleep(duration))
+        .await
+        .unwrap();
+    black_box(item);
+}
+
+async fn sequential_map(data: &[i32], duration: Duration) {
+    if data.is_empty() {
+        return;
+    }
+    for &item in data {
+        timed_work(item, duration).await;
+    }
+}
+
+async fn parallel_map(data: &[i32], duration: Duration) {
+    if data.is_empty() {
+        return;
+    }
+    join_all(data.iter().map(|&item| timed_work(item, duration))).await;
+}
+
+fn bench_async_parallel_map_vs_sequential(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("N=2", vec![1, 2])];
+    let durations = [
+        Duration::ZERO,
+        Duration::from_micros(1),
+        Duration::from_micros(100),
+    ];
+
+    let mut group = c.benchmark_group("124. parallel vs sequential N=2");
+
+    for (label, data) in &inputs {
+        for duration in durations {
+            let id = format!("{label}/work={duration:?}");
+            group.bench_with_input(BenchmarkId::new("sequential", &id), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| sequential_map(black_box(d), duration))
+            });
+            group.bench_with_input(BenchmarkId::new("join_all", &id), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| parallel_map(black_box(d), duration))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 28: MPSC drain - Draining pending work at wake-up
+// A task woken by a notification often empties its queue with `try_recv`
+// and goes back to waiting. `try_recv` is a plain function: it takes no
+// `Context`, so it never registers the task's waker with the channel and
+// never charges tokio's cooperative budget. `recv().await` builds a future
+// and goes through `poll_recv`, which does both each time it is polled: on an
+// open, empty channel it stores the waker and returns `Pending`. Here the
+// channel is closed so the `recv` loop terminates; the `try_recv` loop stops
+// at the first `Empty` or `Disconnected`, whichever comes first.
+// ============================================================
+
+async fn async_work_with_item(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+async fn async_loop_mpsc_drain(mut rx: mpsc::UnboundedReceiver<i32>) {
+    while let Ok(item) = rx.try_recv() {
+        async_work_with_item(item).await;
+    }
+}
+
+async fn async_loop_mpsc_recv(mut rx: mpsc::UnboundedReceiver<i32>) {
+    while let Some(item) = rx.recv().await {
+        async_work_with_item(item).await;
+    }
+}
+
+/// An unbounded receiver holding `prefill` items whose sender is gone.
+fn closed_unbounded_channel(prefill: i32) -> mpsc::UnboundedReceiver<i32> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    for item in 0..prefill {
+        tx.send(item).unwrap();
+    }
+    rx
+}
+
+fn bench_mpsc_drain(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("28. MPSC drain");
+
+    for (label, prefill) in [("empty", 0), ("pre-filled (10 items)", 10)] {
+        group.bench_function(BenchmarkId::new("try_recv", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_unbounded_channel(prefill),
+                async_loop_mpsc_drain,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("recv().await", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || closed_unbounded_channel(prefill),
+                async_loop_mpsc_recv,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_epoch: reclaim_in_epoch with [0, 1, 16] retired nodes
+// `no_check` reads and advances the shared epoch on every pass, as a
+// reclaimer without the fast path does; for an empty list that is two
+// atomic operations, the second a read-modify-write on a shared cache line.
+// ============================================================
+
+async fn free_node(epoch: u64, node: Box<[i32]>) {
+    tokio::task::yield_now().await;
+    black_box((epoch, node));
+}
+
+fn retired_nodes(count: i32) -> Vec<Box<[i32]>> {
+    (0..count).map(|i| Box::from([i])).collect()
+}
+
+fn bench_async_epoch(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let guard = EpochGuard::new();
+
+    let mut group = c.benchmark_group("async_epoch: reclaim_in_epoch");
+
+    for count in [0, 1, 16] {
+        group.bench_function(BenchmarkId::new("with_check", count), |b| {
+            b.to_async(&rt).iter_batched(
+                || retired_nodes(count),
+                |garbage| reclaim_in_epoch(&guard, garbage, free_node),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", count), |b| {
+            b.to_async(&rt).iter_batched(
+                || retired_nodes(count),
+                |garbage| async {
+                    let epoch = guard.current();
+                    for node in garbage {
+                        free_node(epoch, node).await;
+                    }
+                    guard.advance();
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 29: tracing::instrument overhead - Observability on async loops
+// `#[instrument]` wraps the body in `Instrumented<F>`, which creates a span
+// when the fn is called and enters/exits it around every poll. The guard
+// inside the fn runs after that, so it saves only the iterator setup, not the
+// span; checking at the call site skips the span as well.
+// `skip_all` keeps the argument out of the span; recording `data` would add
+// a `Debug` format of the whole Vec per call.
+// With no subscriber every span is disabled and the cost is a cached
+// interest check. With a `fmt` subscriber (writing to `io::sink`) each span
+// is registered, entered and closed, and that structured-logging bookkeeping
+// can cost far more than the async loop it describes.
+// ============================================================
+
+#[tracing::instrument(skip_all)]
+async fn traced_with_check(data: &[i32]) {
+    if !data.is_empty() {
+        async_work().await;
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn traced_no_check(data: &[i32]) {
+    for _ in data {
+        async_work().await;
+    }
+}
+
+fn bench_tracing_instrument(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("1 item", vec![1])];
+
+    let mut group = c.benchmark_group("29. tracing::instrument overhead");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("plain/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| with_check_slice(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("plain/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| no_check_slice(black_box(d)))
+        });
+
+        for subscriber in ["no subscriber", "fmt subscriber"] {
+            // `to_async` polls on this thread, so a thread-local default applies.
+            let _default = (subscriber == "fmt subscriber").then(|| {
+                tracing::subscriber::set_default(
+                    tracing_subscriber::fmt().with_writer(io::sink).finish(),
+                )
+            });
+
+            let id = |case: &str| BenchmarkId::new(format!("traced ({subscriber})/{case}"), label);
+            group.bench_with_input(id("with_check"), data, |b, d| {
+                b.to_async(&rt).iter(|| traced_with_check(black_box(d)))
+            });
+            group.bench_with_input(id("no_check"), data, |b, d| {
+                b.to_async(&rt).iter(|| traced_no_check(black_box(d)))
+            });
+            group.bench_with_input(id("call-site check"), data, |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        traced_no_check(data).await;
+                    }
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 125: idempotency check - An async guard in front of the loop
+// Before processing a retried request, services ask a store whether the
+// operation already completed (Redis SETNX, a unique-key insert). That check
+// is itself async: here a round-trip is modelled as one `yield_now` plus a
+// lookup under a `tokio::sync::Mutex`. It costs orders of magnitude more
+// than `is_empty()`, so guarding after it makes the empty-check irrelevant;
+// guarding before it lets empty operations skip the round-trip entirely.
+// The operation id is never recorded, so every check misses and the loop runs.
+// ============================================================
+
+#[derive(Default)]
+struct IdempotencyStore {
+    completed: Mutex<HashSet<u64>>,
+}
+
+impl IdempotencyStore {
+    async fn operation_already_completed(&self, id: u64) -> bool {
+        tokio::task::yield_now().await;
+        self.completed.lock().await.contains(&id)
+    }
+}
+
+async fn process_idempotent<const GUARD_FIRST: bool, const GUARD_AFTER: bool>(
+    store: &IdempotencyStore,
+    id: u64,
+    data: &[i32],
+) {
+    if GUARD_FIRST && data.is_empty() {
+        return;
+    }
+    if store.operation_already_completed(id).await {
+        return;
+    }
+    if GUARD_AFTER && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_async_idempotent_ops(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let store = IdempotencyStore::default();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("125. idempotency check");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("empty-check only", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    async_loop_borrowed(data).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("idempotency only", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| process_idempotent::<false, false>(&store, 1, black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("idempotency then empty-check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| process_idempotent::<false, true>(&store, 1, black_box(d)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("empty-check then idempotency", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| process_idempotent::<true, false>(&store, 1, black_box(d)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 30: poll_fn vs async fn - Hand-written vs generated futures
+// `poll_fn_loop` skips the generated resume-state switch and returned-state
+// bookkeeping (Cases M/N in `src/mir_demo.rs`). Both loops have no
+// suspension point, which is the only shape `poll_fn` expresses without
+// boxing; the state-machine sizes are printed once for reference.
+// Both futures are 32 bytes (`poll_fn_loop` holds the `IntoIter`, the async
+// fn its `Vec` plus a state tag) and their timings land within a few ns of
+// each other, generated code sometimes ahead. `poll_fn` is worth it for
+// wrapping poll-based APIs, not for speed.
+// ============================================================
+
+fn bench_poll_fn_vs_async_fn(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    println!(
+        "future sizes: async_fn_loop = {} bytes, poll_fn_loop = {} bytes",
+        mem::size_of_val(&async_fn_loop(Vec::new())),
+        mem::size_of_val(&poll_fn_loop(Vec::new())),
+    );
+
+    let mut group = c.benchmark_group("30. poll_fn vs async fn");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("async fn/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter_batched(
+                    || d.clone(),
+                    |data| async move {
+                        if !data.is_empty() {
+                            async_fn_loop(data).await;
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("async fn/no_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter_batched(|| d.clone(), async_fn_loop, BatchSize::SmallInput)
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("poll_fn/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter_batched(
+                    || d.clone(),
+                    |data| async move {
+                        if !data.is_empty() {
+                            poll_fn_loop(data).await;
+                        }
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("poll_fn/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter_batched(|| d.clone(), poll_fn_loop, BatchSize::SmallInput)
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 126: feature flags in loop - Hoisting the flag check
+// A flag lookup (here a `HashMap<&str, bool>` probe, hashing the name each
+// time) inside the loop is paid once per item. Flags do not change mid-loop,
+// so hoisting the lookup above the loop makes it once per call. For empty
+// data even that one lookup is wasted; the empty-check in front skips it, so
+// with the guard it no longer matters where the flag is read.
+// ============================================================
+
+struct FeatureFlags(HashMap<&'static str, bool>);
+
+impl FeatureFlags {
+    fn is_enabled(&self, name: &str) -> bool {
+        self.0.get(name).copied().unwrap_or(false)
+    }
+}
+
+async fn new_process(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item.wrapping_mul(2));
+}
+
+async fn old_process(item: i32) {
+    tokio::task::yield_now().await;
+    black_box(item);
+}
+
+async fn flag_per_item(flags: &FeatureFlags, data: &[i32]) {
+    for &item in data {
+        if flags.is_enabled("new_feature") {
+            new_process(item).await;
+        } else {
+            old_process(item).await;
+        }
+    }
+}
+
+async fn flag_hoisted<const CHECK: bool>(flags: &FeatureFlags, data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    if flags.is_enabled("new_feature") {
+        for &item in data {
+            new_process(item).await;
+        }
+    } else {
+        for &item in data {
+            old_process(item).await;
+        }
+    }
+}
+
+fn bench_async_feature_flags(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let flags = FeatureFlags(HashMap::from([("new_feature", true), ("dark_mode", false)]));
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("126. feature flags in loop");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("per-item check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| flag_per_item(&flags, black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("hoisted", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| flag_hoisted::<false>(&flags, black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("hoisted/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| flag_hoisted::<true>(&flags, black_box(d)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 31: Waker overhead - Driving futures without an executor
+// The futures are polled by hand with a fixed `Context`, so the numbers
+// contain only the state machine, not tokio's scheduler. A future touches
+// its waker only on the way to `Pending`; `with_check` and `no_check` both
+// finish on the first poll, so on empty input (and with one item, since
+// `async_work` is immediately ready) neither clones or wakes it. The counts
+// are printed once, with a yielding loop for contrast: `yield_now` outside a
+// runtime calls `wake_by_ref` once per item.
+// ============================================================
+
+fn poll_once<F: Future>(fut: F, cx: &mut Context<'_>) -> Poll<F::Output> {
+    pin!(fut).poll(cx)
+}
+
+fn waker_counts<F: Future>(fut: F) -> WakerCounts {
+    let counter = CountingWaker::new();
+    let waker = counter.waker();
+    let mut fut = pin!(fut);
+    while fut
+        .as_mut()
+        .poll(&mut Context::from_waker(&waker))
+        .is_pending()
+    {}
+    counter.counts()
+}
+
+fn bench_waker_overhead(c: &mut Criterion) {
+    let empty_data: Vec<i32> = vec![];
+    let one_item = vec![1];
+
+    for (label, data) in [("empty", &empty_data), ("1 item", &one_item)] {
+        println!(
+            "waker interactions ({label}): with_check {}, no_check {}, yield loop {:?}",
+            waker_counts(with_check(data)).interactions(),
+            waker_counts(no_check(data)).interactions(),
+            waker_counts(async_loop_with_await(data.clone())),
+        );
+    }
+
+    let counter = CountingWaker::new();
+    let wakers: [(&str, Waker); 3] = [
+        ("futures noop", futures::task::noop_waker_ref().clone()),
+        ("custom noop", noop_waker()),
+        ("counting", counter.waker()),
+    ];
+
+    let mut group = c.benchmark_group("31. Waker overhead");
+
+    for (name, waker) in &wakers {
+        let mut cx = Context::from_waker(waker);
+        group.bench_function(BenchmarkId::new("with_check", name), |b| {
+            b.iter(|| poll_once(with_check(black_box(&empty_data)), &mut cx))
+        });
+        group.bench_function(BenchmarkId::new("no_check", name), |b| {
+            b.iter(|| poll_once(no_check(black_box(&empty_data)), &mut cx))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_histogram: update_histogram with [0, 100, 10000] values
+// With 10000 values the loop exhausts tokio's per-task budget (128 units)
+// many times over and yields each time; `no_check` is the same loop without
+// the empty fast path. Throughput is per recorded value.
+// ============================================================
+
+fn bench_async_histogram(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut histogram = Histogram::<u64>::new_with_bounds(1, 1_000_000, 3).unwrap();
+
+    let mut group = c.benchmark_group("async_histogram: update_histogram");
+
+    for count in [0u64, 100, 10_000] {
+        let values: Vec<u64> = (0..count).map(|i| 1 + i * 97 % 1_000_000).collect();
+        group.throughput(Throughput::Elements(count));
+        group.bench_with_input(BenchmarkId::new("with_check", count), &values, |b, v| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        update_histogram(black_box(v).iter().copied(), &mut histogram).await;
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", count), &values, |b, v| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        for &value in black_box(v) {
+                            histogram.saturating_record(value);
+                            tokio::task::coop::consume_budget().await;
+                        }
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 32: unconstrained - Opting out of the cooperative budget
+// Each item awaits `consume_budget()`, standing in for a tokio resource
+// (channel, socket, timer) that charges one unit of the task's budget. Once
+// the budget (128 units per poll) runs out, the next charge forces a yield
+// back to the scheduler. `unconstrained` disables that, so the 1024-item
+// loop runs straight through; 0, 1 and 64 items never exhaust the budget, and
+// an empty loop never charges it at all. The wrapper is not free: it swaps
+// the task's budget out and back on every poll, which shows up as a few ns
+// on the empty and 1-item cases. The forced yields it saves are cheap here,
+// where the benchmark task is alone on the runtime and is re-polled at once;
+// on a busy scheduler each one waits behind other tasks.
+//
+// Starvation: an unconstrained task that always has work never yields, so
+// other tasks on its worker (and timers, and I/O) wait until it finishes.
+// Keep unconstrained sections short, or yield manually.
+// ============================================================
+
+async fn budgeted_loop<const CHECK: bool>(data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        tokio::task::coop::consume_budget().await;
+        black_box(item);
+    }
+}
+
+fn bench_unconstrained(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("32. unconstrained");
+
+    for len in [0, 1, 64, 1024] {
+        let data: Vec<i32> = (0..len).collect();
+        group.bench_with_input(
+            BenchmarkId::new("constrained/with_check", len),
+            &data,
+            |b, d| b.to_async(&rt).iter(|| budgeted_loop::<true>(black_box(d))),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("constrained/no_check", len),
+            &data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| budgeted_loop::<false>(black_box(d)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("unconstrained/with_check", len),
+            &data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| tokio::task::unconstrained(budgeted_loop::<true>(black_box(d))))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("unconstrained/no_check", len),
+            &data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| tokio::task::unconstrained(budgeted_loop::<false>(black_box(d))))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 127: cache warming - Startup fill vs already-warm passes
+// At startup every key is missing and the warming loop does real work. At
+// runtime the same pass is re-run (after a config reload, a reconnect) and
+// finds every key already cached, so the list of keys to warm is empty and
+// the guard is all that runs. "runtime" includes working out which keys are
+// missing, which is paid either way.
+// ============================================================
+
+async fn compute(key: u64) -> u64 {
+    tokio::task::yield_now().await;
+    black_box(key.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+async fn warm_cache<const CHECK: bool>(cache: &mut HashMap<u64, u64>, keys_to_warm: Vec<u64>) {
+    if CHECK && keys_to_warm.is_empty() {
+        return;
+    }
+    for key in keys_to_warm {
+        let value = compute(key).await;
+        cache.insert(key, value);
+    }
+}
+
+fn missing_keys(cache: &HashMap<u64, u64>, keys: &[u64]) -> Vec<u64> {
+    keys.iter()
+        .copied()
+        .filter(|k| !cache.contains_key(k))
+        .collect()
+}
+
+fn bench_warm<const CHECK: bool>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    rt: &Runtime,
+    keys: &[u64],
+) {
+    let case = if CHECK { "with_check" } else { "no_check" };
+    group.bench_function(
+        BenchmarkId::new(format!("startup/{case}"), keys.len()),
+        |b| {
+            b.to_async(rt).iter_batched(
+                || (HashMap::with_capacity(keys.len()), keys.to_vec()),
+                |(mut cache, keys)| async move {
+                    warm_cache::<CHECK>(&mut cache, keys).await;
+                    cache
+                },
+                BatchSize::SmallInput,
+            )
+        },
+    );
+
+    let mut warm: HashMap<u64, u64> = keys.iter().map(|&k| (k, k)).collect();
+    group.bench_function(
+        BenchmarkId::new(format!("runtime/{case}"), keys.len()),
+        |b| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        let keys_to_warm = missing_keys(&warm, black_box(keys));
+                        warm_cache::<CHECK>(&mut warm, keys_to_warm).await;
+                    }
+                    start.elapsed()
+                })
+            })
+        },
+    );
+}
+
+fn bench_async_cache_warming(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("127. cache warming");
+
+    for count in [0u64, 10, 100] {
+        let keys: Vec<u64> = (0..count).collect();
+        bench_warm::<true>(&mut group, &rt, &keys);
+        bench_warm::<false>(&mut group, &rt, &keys);
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_rollup: rollup_window with [0, 100, 10000] events
+// Events arrive out of order (a fixed shuffle of timestamps over ~10 s) and
+// are rolled up into 1 s windows. The empty tick returns before the sort.
+// ============================================================
+
+struct Sample {
+    at: Duration,
+    value: f64,
+}
+
+impl TimeseriesEvent for Sample {
+    fn timestamp(&self) -> Duration {
+        self.at
+    }
+
+    fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+fn samples(count: u64) -> Vec<Sample> {
+    (0..count)
+        .map(|i| Sample {
+            at: Duration::from_millis(i * 7919 % 10_000),
+            value: (i % 100) as f64,
+        })
+        .collect()
+}
+
+fn bench_async_rollup(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_rollup: rollup_window");
+
+    for count in [0u64, 100, 10_000] {
+        group.bench_function(BenchmarkId::from_parameter(count), |b| {
+            b.to_async(&rt).iter_batched(
+                || samples(count),
+                |events| rollup_window(events, Duration::from_secs(1)),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 33: Drop cost - Cancelling a loop future mid-iteration
+// When a `select!` arm loses, its future is dropped in whatever state it is
+// in, and dropping runs the drop glue for exactly the fields that state
+// holds. Unpolled, that is the captured `Vec`; suspended at the `yield_now`,
+// it is the `vec::IntoIter` (which still owns the buffer) plus the awaitee;
+// completed, nothing. Only the timed routine drops; building and polling the
+// future happen in setup. One poll is made with `poll_immediate`.
+// The boxed state machine's size bounds how much memory is live in any
+// state, and is printed once; the drop itself only frees the heap parts.
+// Measured: the box's own deallocation is the floor (~20 ns); a live Vec
+// buffer, whether unpolled or mid-iteration, roughly doubles it.
+// ============================================================
+
+async fn loop_half_complete(data: Vec<i32>) {
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn polled_once(data: Vec<i32>) -> Pin<Box<impl Future<Output = ()>>> {
+    let mut fut = Box::pin(loop_half_complete(data));
+    futures::executor::block_on(poll_immediate(&mut fut));
+    fut
+}
+
+fn bench_drop_cost(c: &mut Criterion) {
+    let items: Vec<i32> = (0..8).collect();
+
+    println!(
+        "loop_half_complete state machine: {} bytes",
+        mem::size_of_val(&loop_half_complete(Vec::new()))
+    );
+
+    let mut group = c.benchmark_group("33. Drop cost");
+
+    group.bench_function("unpolled/empty", |b| {
+        b.iter_batched(
+            || Box::pin(loop_half_complete(Vec::new())),
+            mem::drop,
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("unpolled/8 items", |b| {
+        b.iter_batched(
+            || Box::pin(loop_half_complete(items.clone())),
+            mem::drop,
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("mid-iteration/8 items", |b| {
+        b.iter_batched(
+            || polled_once(items.clone()),
+            mem::drop,
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("completed/empty", |b| {
+        b.iter_batched(|| polled_once(Vec::new()), mem::drop, BatchSize::SmallInput)
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 128: allocator background ops - Loops under allocation pressure
+// Rust has no GC, and this crate uses the system allocator rather than
+// jemalloc, so there is no allocator background thread to provoke. The
+// closest stand-in is a second thread that keeps allocating and freeing
+// buffers of mixed sizes, contending for the allocator's arenas and the
+// cache. Neither loop variant allocates on its own path, so any shift comes
+// from cache and scheduler interference; the question is whether the
+// empty-check's margin over the unguarded loop survives it.
+// On a single-core machine the pressure thread also takes time slices from
+// the benchmark thread. Measured here, that roughly doubles every case
+// uniformly, so the ratios between variants hold under pressure.
+// ============================================================
+
+struct AllocPressure {
+    stop: Arc<AtomicBool>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl AllocPressure {
+    fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            let mut sizes = [16usize, 256, 4096, 64 * 1024].into_iter().cycle();
+            let mut live: VecDeque<Vec<u8>> = VecDeque::with_capacity(64);
+            while !flag.load(Ordering::Relaxed) {
+                live.push_back(vec![1u8; sizes.next().unwrap()]);
+                if live.len() == 64 {
+                    live.pop_front();
+                }
+            }
+        });
+        AllocPressure {
+            stop,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for AllocPressure {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            handle.join().unwrap();
+        }
+    }
+}
+
+fn bench_async_memory_pressure_gc(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("128. allocator background ops");
+
+    for pressure in [false, true] {
+        let _pressure = pressure.then(AllocPressure::start);
+        let mode = if pressure { "pressure" } else { "quiet" };
+        for (label, data) in &inputs {
+            let id = format!("{mode}/{label}");
+            group.bench_with_input(BenchmarkId::new("with_check", &id), data, |b, d| {
+                b.to_async(&rt).iter(|| with_check(black_box(d)))
+            });
+            group.bench_with_input(BenchmarkId::new("no_check", &id), data, |b, d| {
+                b.to_async(&rt).iter(|| no_check(black_box(d)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 34: select! scaling - Loop arm racing N pending arms
+// `tokio::select!` polls its branches in a random order each time, so on
+// average half of the `pending()` arms are polled before the loop arm gets
+// its turn and wins. Each extra arm adds one poll of a trivially pending
+// future plus some generated bookkeeping. With the empty-check the loop arm
+// itself is a constant, immediately-ready poll; any growth across 1/2/4/8
+// comes from the select machinery, not from the loop.
+// Measured: ~20 ns at one pending arm to ~55-60 ns at eight for both
+// variants; the guard's few-ns difference is within the random-order noise.
+// `select!` fixes its arm count at compile time, hence one function per N.
+// ============================================================
+
+async fn select_loop_arm<const CHECK: bool>(data: Vec<i32>) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    async_loop_with_await(data).await;
+}
+
+async fn select_1<const CHECK: bool>(data: Vec<i32>) {
+    tokio::select! {
+        _ = select_loop_arm::<CHECK>(data) => {}
+        _ = pending::<()>() => {}
+    }
+}
+
+async fn select_2<const CHECK: bool>(data: Vec<i32>) {
+    tokio::select! {
+        _ = select_loop_arm::<CHECK>(data) => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+    }
+}
+
+async fn select_4<const CHECK: bool>(data: Vec<i32>) {
+    tokio::select! {
+        _ = select_loop_arm::<CHECK>(data) => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+    }
+}
+
+async fn select_8<const CHECK: bool>(data: Vec<i32>) {
+    tokio::select! {
+        _ = select_loop_arm::<CHECK>(data) => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+        _ = pending::<()>() => {}
+    }
+}
+
+fn bench_select_scaling(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("34. select! scaling");
+
+    group.bench_function("with_check/1 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_1::<true>(black_box(Vec::new())))
+    });
+    group.bench_function("no_check/1 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_1::<false>(black_box(Vec::new())))
+    });
+    group.bench_function("with_check/2 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_2::<true>(black_box(Vec::new())))
+    });
+    group.bench_function("no_check/2 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_2::<false>(black_box(Vec::new())))
+    });
+    group.bench_function("with_check/4 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_4::<true>(black_box(Vec::new())))
+    });
+    group.bench_function("no_check/4 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_4::<false>(black_box(Vec::new())))
+    });
+    group.bench_function("with_check/8 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_8::<true>(black_box(Vec::new())))
+    });
+    group.bench_function("no_check/8 pending", |b| {
+        b.to_async(&rt)
+            .iter(|| select_8::<false>(black_box(Vec::new())))
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 35: Pre-allocated Vec - Capacity does not defeat the guard
+// `Vec::is_empty()` is `self.len() == 0`; it reads `len`, never `capacity`,
+// so a `Vec::with_capacity(64)` that was never filled takes the same fast
+// path as `Vec::new()`. The capacity only matters when the Vec is dropped:
+// a zero-capacity Vec owns no buffer, a pre-allocated one must free it,
+// whichever variant runs. Vecs are built in setup so only that drop and the
+// loop (or guard) are timed. Measured: freeing the 64-slot buffer adds
+// ~20 ns to both variants, dwarfing the guard's own few-ns difference.
+// ============================================================
+
+fn bench_preallocated_vec(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let capacities: [(&str, usize); 2] = [("Vec::new", 0), ("with_capacity(64)", 64)];
+
+    let mut group = c.benchmark_group("35. Pre-allocated Vec");
+
+    for (label, capacity) in capacities {
+        group.bench_function(BenchmarkId::new("with_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || Vec::with_capacity(capacity),
+                |data| async move {
+                    if !black_box(&data).is_empty() {
+                        async_loop_with_await(data).await;
+                    }
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("no_check", label), |b| {
+            b.to_async(&rt).iter_batched(
+                || Vec::with_capacity(capacity),
+                |data| async_loop_with_await(black_box(data)),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 129: interrupt coalescing - One loop per event vs per batch
+// A handler that runs once per interrupt pays its fixed cost (a `Vec` for the
+// batch, the call, the empty-check) N times for N events. Coalescing collects
+// everything pending at wake-up and runs the loop once, so that cost is paid
+// once and amortised over N, the coalescing factor. Both variants drain the
+// same pre-filled, closed channel and do the same per-event work.
+// Measured: equal at N=1, ~30% faster coalesced at N=100. The saving is
+// mostly the per-event `Vec` allocation; the check itself is a fraction.
+// ============================================================
+
+async fn collect_pending_events(rx: &mut mpsc::UnboundedReceiver<i32>) -> Vec<i32> {
+    let mut events = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        events.push(event);
+    }
+    events
+}
+
+async fn handle_events(events: Vec<i32>) {
+    if !events.is_empty() {
+        for event in events {
+            async_work_with_item(event).await;
+        }
+    }
+}
+
+async fn one_at_a_time(mut rx: mpsc::UnboundedReceiver<i32>) {
+    while let Ok(event) = rx.try_recv() {
+        handle_events(vec![event]).await;
+    }
+}
+
+async fn coalesced(mut rx: mpsc::UnboundedReceiver<i32>) {
+    let events = collect_pending_events(&mut rx).await;
+    handle_events(events).await;
+}
+
+fn bench_async_interrupt_coalescing(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("129. interrupt coalescing");
+
+    for n in [1, 10, 100] {
+        group.bench_with_input(BenchmarkId::new("one-at-a-time", n), &n, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || closed_unbounded_channel(n),
+                one_at_a_time,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("coalesced", n), &n, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || closed_unbounded_channel(n),
+                coalesced,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 36: seq_and_then - Chaining two loops through an Option
+// The first loop keeps the even items and returns `None` when there are
+// none; the second only runs on `Some`. `seq_and_then` is the same `if let`
+// wrapped in a combinator, so the two forms should compile to the same state
+// machine: on empty input the second loop's future is never constructed in
+// either. Measured: the combinator is ~5 ns slower on empty input, the cost
+// of moving the already-built first future into its own state machine.
+// ============================================================
+
+async fn collect_evens(data: Vec<i32>) -> Option<Vec<i32>> {
+    let mut evens = Vec::new();
+    for item in data {
+        tokio::task::yield_now().await;
+        if item % 2 == 0 {
+            evens.push(item);
+        }
+    }
+    (!evens.is_empty()).then_some(evens)
+}
+
+async fn consume_evens(evens: Vec<i32>) {
+    for item in evens {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_seq_and_then(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("36. seq_and_then");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("seq_and_then", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| seq_and_then(collect_evens(black_box(d).clone()), consume_evens))
+        });
+        group.bench_with_input(BenchmarkId::new("if let", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let result = collect_evens(black_box(d).clone()).await;
+                if let Some(r) = result {
+                    consume_evens(r).await;
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_dag_executor: execute_dag over [0, 5, 20] nodes
+// Linear puts one task per level, wide puts every task on level 0, and the
+// balanced tree doubles the level width going up from a single root. None of
+// these leave a level empty; the sparse layout puts tasks on every other
+// level, which is where the guard has `join_all`s to skip. The number of
+// skipped levels per DAG is printed before the group runs.
+// Measured: the dense layouts are even; sparse/20 (19 empty levels) runs
+// ~20 ns per skipped level faster with the guard.
+// ============================================================
+
+/// A DAG node that completes after one scheduler round-trip.
+struct LocalTask(i32);
+
+impl Task for LocalTask {
+    type Output = i32;
+
+    async fn run(&self) -> i32 {
+        tokio::task::yield_now().await;
+        black_box(self.0)
+    }
+}
+
+fn build_dag(topology: &str, nodes: usize) -> Dag<LocalTask> {
+    let mut dag = Dag::new();
+    for i in 0..nodes {
+        let level = match topology {
+            "linear" => i,
+            "wide" => 0,
+            "balanced tree" => (usize::BITS - 1 - (i + 1).leading_zeros()) as usize,
+            "sparse" => 2 * i,
+            _ => unreachable!(),
+        };
+        dag.add_task(level, LocalTask(i as i32));
+    }
+    dag
+}
+
+async fn execute_dag_no_check(dag: &Dag<LocalTask>) -> Vec<i32> {
+    let mut outputs = Vec::new();
+    for level in dag.levels() {
+        outputs.extend(join_all(level.iter().map(Task::run)).await);
+    }
+    outputs
+}
+
+fn bench_async_dag_executor(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let topologies = ["linear", "wide", "balanced tree", "sparse"];
+
+    for topology in topologies {
+        for nodes in [0, 5, 20] {
+            let dag = build_dag(topology, nodes);
+            println!(
+                "{topology}/{nodes} nodes: {} levels, {} empty",
+                dag.levels().len(),
+                dag.empty_levels()
+            );
+        }
+    }
+
+    let mut group = c.benchmark_group("async_dag_executor: execute_dag");
+
+    for topology in topologies {
+        for nodes in [0, 5, 20] {
+            let dag = build_dag(topology, nodes);
+            let id = format!("{topology}/{nodes} nodes");
+            group.bench_with_input(BenchmarkId::new("with_check", &id), &dag, |b, dag| {
+                b.to_async(&rt).iter(|| execute_dag(black_box(dag)))
+            });
+            group.bench_with_input(BenchmarkId::new("no_check", &id), &dag, |b, dag| {
+                b.to_async(&rt)
+                    .iter(|| execute_dag_no_check(black_box(dag)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 37: Yield frequency - Yielding every N items
+// A loop that yields on every item pays a scheduler round-trip per item, and
+// that dominates its cost; yielding every `n` items amortises the round-trip
+// over `n` items of plain loop work, so the 64-item cost falls steeply from
+// n = 1 and flattens once the loop body outweighs the yields. `i = 0` always
+// passes `i % n == 0`, so even `usize::MAX` yields once.
+// The empty-check sits outside that curve: an empty call never reaches the
+// `i % n` test, so what the guard saves (building and polling the future
+// once, ~6-8 ns here) is the same at every `n`. What changes is how large
+// that saving is next to a non-empty call, which shrinks ~8x from n = 1 to
+// n = usize::MAX; the guard is worth relatively more for rarely-yielding
+// loops.
+// ============================================================
+
+async fn loop_yield_every_n(data: Vec<i32>, n: usize) {
+    for (i, item) in data.into_iter().enumerate() {
+        if i % n == 0 {
+            tokio::task::yield_now().await;
+        }
+        black_box(item);
+    }
+}
+
+fn bench_yield_frequency(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("64 items", (0..64).collect())];
+
+    let mut group = c.benchmark_group("37. Yield frequency");
+
+    for (every, n) in [("1", 1), ("4", 4), ("16", 16), ("usize::MAX", usize::MAX)] {
+        for (label, data) in &inputs {
+            let id = format!("every {every}/{label}");
+            group.bench_with_input(BenchmarkId::new("with_check", &id), data, |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        loop_yield_every_n(data.clone(), n).await;
+                    }
+                })
+            });
+            group.bench_with_input(BenchmarkId::new("no_check", &id), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| loop_yield_every_n(black_box(d).clone(), n))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 130: SIMD + async batch - Guarding the send loop after compute
+// `std::simd` is still nightly-only, so `simd_process_batch` works in
+// fixed 8-lane chunks that LLVM auto-vectorises on stable, with a scalar
+// tail. The empty-check runs after the compute step on its output, so on a
+// non-empty batch it is one length compare next to a vectorised pass and N
+// awaited sends, and only an empty batch actually skips anything.
+// Measured: the compute pass is 30-45 ns for 8-32 items, the sends ~35 ns
+// each; with_check and no_check differ by run-to-run noise on every size.
+// ============================================================
+
+const SIMD_LANES: usize = 8;
+
+fn simd_process_batch(data: &[i32]) -> Vec<i32> {
+    let mut results = Vec::with_capacity(data.len());
+    let mut chunks = data.chunks_exact(SIMD_LANES);
+    for chunk in &mut chunks {
+        let mut lanes = [0i32; SIMD_LANES];
+        for (lane, &x) in lanes.iter_mut().zip(chunk) {
+            *lane = x.wrapping_mul(3).wrapping_add(1);
+        }
+        results.extend_from_slice(&lanes);
+    }
+    results.extend(
+        chunks
+            .remainder()
+            .iter()
+            .map(|&x| x.wrapping_mul(3).wrapping_add(1)),
+    );
+    results
+}
+
+async fn simd_then_send<const CHECK: bool>(data: &[i32]) {
+    let results = simd_process_batch(data);
+    if CHECK && results.is_empty() {
+        return;
+    }
+    for result in results {
+        async_work_with_item(result).await;
+    }
+}
+
+fn bench_async_vectorized_batch(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("130. SIMD + async batch");
+
+    for size in [0, 8, 16, 32] {
+        let data: Vec<i32> = (0..size).collect();
+        group.bench_with_input(BenchmarkId::new("simd only", size), &data, |b, d| {
+            b.iter(|| simd_process_batch(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("with_check", size), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| simd_then_send::<true>(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", size), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| simd_then_send::<false>(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 52: Runtime topology - current_thread vs work-stealing multi_thread
+// `block_on` polls its future on the bench thread whatever the runtime, so
+// each iteration spawns the loop instead: on `current_thread` the task runs
+// on the bench thread inside `block_on`, on `multi_thread` it goes through
+// the inject queue to a worker, and its waker must notify that worker
+// across threads. That is the synchronisation the guard is hoped to save more
+// of under work-stealing; the spawn and `JoinHandle` await are paid by both
+// variants alike, and the guard sits inside the task.
+// Measured on one core: the spawn round-trip (~1.8 µs current_thread, ~3.7
+// µs multi_thread) buries the guard; with_check and no_check are within
+// noise on both runtimes.
+// ============================================================
+
+async fn spawned_loop<const CHECK: bool>(data: Vec<i32>) {
+    tokio::spawn(async move {
+        if CHECK && data.is_empty() {
+            return;
+        }
+        async_loop_with_await(data).await;
+    })
+    .await
+    .unwrap();
+}
+
+fn bench_runtime_topology(c: &mut Criterion) {
+    let runtimes = [
+        (
+            "current_thread",
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap(),
+        ),
+        (
+            "multi_thread",
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap(),
+        ),
+    ];
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("64 items", (0..64).collect())];
+
+    let mut group = c.benchmark_group("52. Runtime topology");
+
+    for (label, data) in &inputs {
+        for (topology, rt) in &runtimes {
+            let id = format!("{label}/{topology}");
+            group.bench_with_input(BenchmarkId::new("with_check", &id), data, |b, d| {
+                b.to_async(rt)
+                    .iter(|| spawned_loop::<true>(black_box(d).clone()))
+            });
+            group.bench_with_input(BenchmarkId::new("no_check", &id), data, |b, d| {
+                b.to_async(rt)
+                    .iter(|| spawned_loop::<false>(black_box(d).clone()))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_lsm_tree: maybe_compact over [0, 1, 5] tables
+// Each table holds 64 writes over 16 keys, so compaction sorts and drops
+// three of every four. The 0-table case is the common one for a background
+// compaction check and returns at the guard, handing the empty Vec back
+// instead of building a second one (~9 ns saved here).
+// ============================================================
+
+fn sstables(count: usize) -> Vec<SsTable> {
+    (0..count)
+        .map(|_| SsTable::new((0..64u64).map(|i| ((i * 7) % 16, i)).collect()))
+        .collect()
+}
+
+async fn maybe_compact_no_check(tables: Vec<SsTable>) -> Vec<SsTable> {
+    let mut compacted = Vec::with_capacity(tables.len());
+    for table in tables {
+        compacted.push(compact_table(table).await);
+    }
+    compacted
+}
+
+fn bench_async_lsm_tree(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_lsm_tree: maybe_compact");
+
+    for count in [0, 1, 5] {
+        group.bench_with_input(BenchmarkId::new("with_check", count), &count, |b, &n| {
+            b.to_async(&rt)
+                .iter_batched(|| sstables(n), maybe_compact, BatchSize::SmallInput)
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || sstables(n),
+                maybe_compact_no_check,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 38: Chain vs loop - N inline awaits vs a loop of N
+// On stable, a const-generic `N` cannot be decremented in a type, so the
+// chain recurses through impls: `Awaits<N>` awaits once and then awaits
+// `Awaits<N - 1>`, one impl per N. Each level is its own small state machine
+// nested in its parent, so the chain has N distinct suspend points and its
+// size grows with the nesting, while the loop reuses one suspend point and
+// keeps only the `Range`. The sizes are printed once before the group runs.
+// Measured: each level adds just a state byte (4/7/19 bytes for N = 1/4/16
+// against a flat 24 for the loop), yet the chain is ~20% slower at N = 16:
+// every resume re-enters all the levels above the one that suspended.
+// ============================================================
+
+trait ChainAwaits {
+    fn chain() -> impl Future<Output = ()>;
+}
+
+struct Awaits<const N: usize>;
+
+impl ChainAwaits for Awaits<0> {
+    async fn chain() {}
+}
+
+macro_rules! impl_chain_awaits {
+    ($($n:literal => $prev:literal),*) => {
+        $(impl ChainAwaits for Awaits<$n> {
+            async fn chain() {
+                tokio::task::yield_now().await;
+                Awaits::<$prev>::chain().await;
+            }
+        })*
+    };
+}
+
+impl_chain_awaits!(
+    1 => 0, 2 => 1, 3 => 2, 4 => 3, 5 => 4, 6 => 5, 7 => 6, 8 => 7,
+    9 => 8, 10 => 9, 11 => 10, 12 => 11, 13 => 12, 14 => 13, 15 => 14, 16 => 15
+);
+
+async fn chain_n_awaits<const N: usize>()
+where
+    Awaits<N>: ChainAwaits,
+{
+    Awaits::<N>::chain().await;
+}
+
+async fn loop_n_awaits<const N: usize>() {
+    for _ in 0..N {
+        tokio::task::yield_now().await;
+    }
+}
+
+fn bench_chain_n<const N: usize>(group: &mut BenchmarkGroup<'_, WallTime>, rt: &Runtime)
+where
+    Awaits<N>: ChainAwaits,
+{
+    println!(
+        "N={N}: chain_n_awaits = {} bytes, loop_n_awaits = {} bytes",
+        mem::size_of_val(&chain_n_awaits::<N>()),
+        mem::size_of_val(&loop_n_awaits::<N>())
+    );
+    group.bench_function(BenchmarkId::new("chain", N), |b| {
+        b.to_async(rt).iter(chain_n_awaits::<N>)
+    });
+    group.bench_function(BenchmarkId::new("loop", N), |b| {
+        b.to_async(rt).iter(loop_n_awaits::<N>)
+    });
+}
+
+fn bench_chain_vs_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("38. Chain vs loop");
+
+    bench_chain_n::<1>(&mut group, &rt);
+    bench_chain_n::<4>(&mut group, &rt);
+    bench_chain_n::<16>(&mut group, &rt);
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 131: phase breakdown - Where an async loop spends its overhead
+// Each phase of one loop future's life is timed on its own by driving the
+// future by hand with a no-op waker: setup brings it to the state just
+// before the phase, the routine runs only that phase.
+//   construction   calling the async fn (moves `data` into the state)
+//   first poll     entering the state machine up to the first suspension
+//   per-iteration  resuming after one `yield_now` and suspending again
+//   final poll     the resume that runs off the end and returns `Ready`
+//   drop           dropping the completed future (and its box)
+// For empty data the first poll is also the final one, and the guard skips
+// everything from construction on, leaving just the length check.
+// Measured: the guard ~2 ns; construction ~6 ns; first poll 7-9 ns; a
+// per-iteration resume ~13 ns; the final poll ~30 ns, as completing drops
+// the loop's `IntoIter` and frees its buffer. Drop is ~20 ns but is mostly
+// freeing the box this bench pins into, which an executor pays per task too.
+// ============================================================
+
+fn polled_loop(data: Vec<i32>, polls: usize) -> Pin<Box<impl Future<Output = ()>>> {
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut fut = Box::pin(async_loop_with_await(data));
+    for _ in 0..polls {
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+    }
+    fut
+}
+
+fn bench_async_overhead_distribution(c: &mut Criterion) {
+    let waker = noop_waker();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("131. phase breakdown");
+
+    group.bench_function("guard/empty", |b| {
+        b.iter_batched(
+            Vec::<i32>::new,
+            |data| black_box(&data).is_empty(),
+            BatchSize::SmallInput,
+        )
+    });
+
+    for (label, data) in &inputs {
+        let len = data.len();
+        group.bench_function(BenchmarkId::new("1. construction", label), |b| {
+            b.iter_batched(
+                || data.clone(),
+                |data| async_loop_with_await(black_box(data)),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("2. first poll", label), |b| {
+            b.iter_batched(
+                || polled_loop(data.clone(), 0),
+                |mut fut| {
+                    let _ = fut.as_mut().poll(&mut Context::from_waker(&waker));
+                    fut
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        if len > 1 {
+            group.bench_function(BenchmarkId::new("3. per-iteration poll", label), |b| {
+                b.iter_batched(
+                    || polled_loop(data.clone(), 1),
+                    |mut fut| {
+                        let _ = fut.as_mut().poll(&mut Context::from_waker(&waker));
+                        fut
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        if len > 0 {
+            group.bench_function(BenchmarkId::new("4. final poll", label), |b| {
+                b.iter_batched(
+                    || polled_loop(data.clone(), len),
+                    |mut fut| {
+                        let _ = fut.as_mut().poll(&mut Context::from_waker(&waker));
+                        fut
+                    },
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+        group.bench_function(BenchmarkId::new("5. drop", label), |b| {
+            b.iter_batched(
+                || {
+                    let mut fut = polled_loop(data.clone(), len);
+                    assert!(
+                        fut.as_mut()
+                            .poll(&mut Context::from_waker(&waker))
+                            .is_ready()
+                    );
+                    fut
+                },
+                mem::drop,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 39: Fixed-size array - Guarding `[i32; N]`
+// An array's length is the type-level `N`, so after monomorphization
+// `is_empty()` is a constant: for N > 0 the guard folds away, and for N = 0
+// so does the loop. The MIR (see Cases Q/R in mir_demo) is still generic and
+// keeps both. Measured: N = 0 is even (~1.7 ns), but with_check is slower
+// for N = 1 and 8 (~18 vs ~11 ns at 8) because it copies the array into
+// the state machine and then again into the iterator.
+// ============================================================
+
+fn bench_array_n<const N: usize>(group: &mut BenchmarkGroup<'_, WallTime>, rt: &Runtime) {
+    let data: [i32; N] = std::array::from_fn(|i| i as i32);
+    group.bench_function(BenchmarkId::new("with_check", N), |b| {
+        b.to_async(rt).iter(|| with_check_array(black_box(data)))
+    });
+    group.bench_function(BenchmarkId::new("no_check", N), |b| {
+        b.to_async(rt).iter(|| no_check_array(black_box(data)))
+    });
+}
+
+fn bench_fixed_size_array(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("39. Fixed-size array");
+
+    bench_array_n::<0>(&mut group, &rt);
+    bench_array_n::<1>(&mut group, &rt);
+    bench_array_n::<8>(&mut group, &rt);
+
+    group.finish();
+}
+
+// ============================================================
+// async_schema_migration: run_pending_migrations over [0, 1, 5] migrations
+// With nothing pending the guard returns before the rollback log is set up
+// and the loop starts; a zero-capacity `Vec` does not allocate, so that
+// setup is only a few ns (~3 ns measured at 0). The statements succeed, so
+// no rollback runs in any case.
+// ============================================================
+
+/// A connection whose statements each take one scheduler round-trip.
+struct LocalDb;
+
+impl AsyncDb for LocalDb {
+    async fn execute(&self, statement: &str) -> Result<(), String> {
+        tokio::task::yield_now().await;
+        black_box(statement);
+        Ok(())
+    }
+}
+
+fn migrations(count: u64) -> Vec<Migration> {
+    (1..=count)
+        .map(|v| {
+            Migration::new(
+                v,
+                format!("ALTER TABLE t ADD COLUMN c{v} INT"),
+                format!("ALTER TABLE t DROP COLUMN c{v}"),
+            )
+        })
+        .collect()
+}
+
+async fn run_pending_migrations_no_check(
+    pending: Vec<Migration>,
+    db: &LocalDb,
+) -> Result<(), MigrationError> {
+    let mut applied: Vec<&Migration> = Vec::with_capacity(pending.len());
+    for migration in &pending {
+        if let Err(reason) = db.execute(&migration.up).await {
+            for done in applied.iter().rev() {
+                db.execute(&done.down)
+                    .await
+                    .map_err(|reason| MigrationError::RollbackFailed {
+                        version: done.version,
+                        reason,
+                    })?;
+            }
+            return Err(MigrationError::Failed {
+                version: migration.version,
+                reason,
+            });
+        }
+        applied.push(migration);
+    }
+    Ok(())
+}
+
+fn bench_async_schema_migration(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_schema_migration: run_pending_migrations");
+
+    for count in [0, 1, 5] {
+        group.bench_with_input(BenchmarkId::new("with_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || migrations(n),
+                |pending| run_pending_migrations(pending, &LocalDb),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || migrations(n),
+                |pending| run_pending_migrations_no_check(pending, &LocalDb),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 132: task batching - N spawns vs one spawn per batch
+// Each `tokio::spawn` allocates a task, pushes it to a run queue and hands
+// back a `JoinHandle`; spawning per item pays that N times, spawning one
+// task that loops over the batch pays it once. The guarded batch also skips
+// that one spawn when the batch is empty, so empty input costs a length
+// check. Every variant awaits its handles, so all the work is in the timing.
+// Every spawn goes through `counted_spawn`, and the counts from one run of
+// each variant are printed before the group runs.
+// Measured on one core: empty input is where batching pays (~3 µs for the
+// unguarded spawn vs ~3 ns guarded). With items, the spawn is not the
+// dominant cost: at 100 items the N-spawn variant was ~20% faster, likely
+// because the single batched task yields 100 times in a row, each yield a
+// full trip through the worker's scheduler.
+// ============================================================
+
+static SPAWNS: AtomicUsize = AtomicUsize::new(0);
+
+/// `tokio::spawn`, counting each call in `SPAWNS`.
+fn counted_spawn<F>(fut: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    SPAWNS.fetch_add(1, Ordering::Relaxed);
+    tokio::spawn(fut)
+}
+
+/// Runs `fut` to completion on `rt` and returns how many tasks it spawned.
+fn spawns_made<F: Future>(rt: &Runtime, fut: F) -> usize {
+    SPAWNS.store(0, Ordering::Relaxed);
+    rt.block_on(fut);
+    SPAWNS.load(Ordering::Relaxed)
+}
+
+async fn spawn_per_item(data: Vec<i32>) {
+    let handles: Vec<_> = data
+        .into_iter()
+        .map(|item| counted_spawn(async_work_with_item(item)))
+        .collect();
+    for handle in handles {
+        handle.await.unwrap();
+    }
+}
+
+async fn spawn_batch<const CHECK: bool>(data: Vec<i32>) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    counted_spawn(async move {
+        for item in data {
+            async_work_with_item(item).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+fn bench_async_task_batching(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let sizes = [0i32, 1, 10, 100];
+
+    for n in sizes {
+        let data: Vec<i32> = (0..n).collect();
+        println!(
+            "{n} items: N spawns = {}, 1 spawn = {}, 1 spawn (with_check) = {}",
+            spawns_made(&rt, spawn_per_item(data.clone())),
+            spawns_made(&rt, spawn_batch::<false>(data.clone())),
+            spawns_made(&rt, spawn_batch::<true>(data)),
+        );
+    }
+
+    let mut group = c.benchmark_group("132. task batching");
+
+    for n in sizes {
+        let data: Vec<i32> = (0..n).collect();
+        group.bench_with_input(BenchmarkId::new("N spawns", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| spawn_per_item(black_box(d).clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("1 spawn/no_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| spawn_batch::<false>(black_box(d).clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("1 spawn/with_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| spawn_batch::<true>(black_box(d).clone()))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 40: Arc<Vec> - Shared read access instead of an owned copy
+// Handing each task its own `Vec` means a deep clone per call; handing it an
+// `Arc<Vec<i32>>` means an atomic increment, and a decrement on drop. The
+// guard reads the length through `Arc::deref()`: one load of the Arc's
+// pointer and one of `len` in the shared allocation, which is usually
+// already in cache. So non-empty calls get cheaper with `Arc`, while the
+// guard skips roughly the same future build-and-poll in both forms.
+// Measured: Arc is ~5% faster on 8 items, but the empty-check saves more
+// with it (~18 ns vs ~2 ns): an unguarded empty call still pays the atomic
+// increment and decrement, whereas cloning an empty Vec allocates nothing.
+// ============================================================
+
+async fn async_loop_arc(data: Arc<Vec<i32>>) {
+    for &item in data.iter() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_arc_vec(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("40. Arc<Vec>");
+
+    for (label, data) in &inputs {
+        let shared = Arc::new(data.clone());
+        group.bench_with_input(BenchmarkId::new("Vec/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    async_loop_with_await(data.clone()).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("Vec/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| async_loop_with_await(black_box(d).clone()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("Arc/with_check", label),
+            &shared,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        async_loop_arc(Arc::clone(data)).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("Arc/no_check", label), &shared, |b, d| {
+            b.to_async(&rt)
+                .iter(|| async_loop_arc(Arc::clone(black_box(d))))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 41: flat_map loop - Flattening nested Vecs before the loop
+// `outer.iter().flatten()` is a `Flatten<slice::Iter<Vec<i32>>>`: the outer
+// iterator plus an `Option<slice::Iter<i32>>` for both the front and the back
+// inner iterator, three slice iterators in all, against one for a flat
+// `slice::Iter`. The unguarded loop builds and probes that state even for an
+// empty outer Vec. The guard only checks `outer`, so a non-empty outer with
+// only empty inners still walks every inner Vec to find nothing. The
+// iterator sizes are printed once before the group runs (48 vs 16 bytes);
+// the guard saved ~1 ns on an empty outer and nothing on empty inners.
+// ============================================================
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn flat_map_loop_with_check(outer: &Vec<Vec<i32>>) {
+    if outer.is_empty() {
+        return;
+    }
+    for &item in outer.iter().flatten() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn flat_map_loop_no_check(outer: &Vec<Vec<i32>>) {
+    for &item in outer.iter().flatten() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_flat_map_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<Vec<i32>>); 3] = [
+        ("empty outer", vec![]),
+        ("8 empty inners", vec![vec![]; 8]),
+        ("8x8 items", vec![(0..8).collect(); 8]),
+    ];
+
+    let outer: Vec<Vec<i32>> = Vec::new();
+    println!(
+        "Flatten iterator = {} bytes, slice::Iter = {} bytes",
+        mem::size_of_val(&outer.iter().flatten()),
+        mem::size_of_val(&outer.iter())
+    );
+
+    let mut group = c.benchmark_group("41. flat_map loop");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("with_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| flat_map_loop_with_check(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| flat_map_loop_no_check(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_prefetch_buffer: next_batch with prefetch_count = [1, 4, 16]
+// Each load is a spawned task that yields once and returns a sequence
+// number. A buffer lives across the iterations of a sample, so read-ahead
+// spawned by one call can finish before the next. The guard on `ready`
+// decides whether a call waits: the more loads are in flight, the more
+// likely some have finished by the next call and the wait is skipped. The
+// average batch size per call is printed first as a measure of that.
+// Measured: 1.0 / 3.9 / 11.8 items per call for 1 / 4 / 16, so while the
+// per-call time grows, the time per item falls from ~3.5 µs to ~0.9 µs.
+// ============================================================
+
+async fn prefetch_calls(prefetch_count: usize, calls: u64) -> (Duration, usize) {
+    let mut buffer = PrefetchBuffer::new(prefetch_count);
+    let mut next = 0u64;
+    let mut items = 0;
+    let start = Instant::now();
+    for _ in 0..calls {
+        let batch = buffer
+            .next_batch(|| {
+                next += 1;
+                let n = next;
+                async move {
+                    tokio::task::yield_now().await;
+                    n
+                }
+            })
+            .await;
+        items += black_box(batch).len();
+    }
+    (start.elapsed(), items)
+}
+
+fn bench_async_prefetch_buffer(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    for prefetch_count in [1, 4, 16] {
+        let (_, items) = rt.block_on(prefetch_calls(prefetch_count, 1000));
+        println!(
+            "prefetch_count={prefetch_count}: {:.2} items per next_batch",
+            items as f64 / 1000.0
+        );
+    }
+
+    let mut group = c.benchmark_group("async_prefetch_buffer: next_batch");
+
+    for prefetch_count in [1, 4, 16] {
+        group.bench_function(BenchmarkId::new("prefetch_count", prefetch_count), |b| {
+            b.iter_custom(|iters| rt.block_on(prefetch_calls(prefetch_count, iters)).0)
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 133: ownership transfer modes - Move, clone or borrow each item
+// `into_iter()` moves each item out and consumes the Vec; `iter().cloned()`
+// leaves the Vec intact but clones every item, which for `String` and
+// `Vec<u8>` is a heap allocation per item; `&data` lends each item and
+// copies nothing. The caller's Vec comes from setup in all three, so each
+// variant also pays for dropping it. For empty input no item is moved,
+// cloned or borrowed, so the guard saves the same future build-and-poll
+// whatever the mode and item type.
+// Measured: the guard saves ~1 ns on empty input in every mode, near the
+// noise. Cloning is what costs: ~100-150 ns more over 8 `String` or
+// `Vec<u8>` items than moving or borrowing, and nothing extra for `i32`.
+// (Empty `move` runs at ~20 ns for the heap types with or without the
+// guard, against ~8 ns for the others; the cause was not tracked down.)
+// ============================================================
+
+async fn transfer_move<const CHECK: bool, T>(data: Vec<T>) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn transfer_clone<const CHECK: bool, T: Clone>(data: Vec<T>) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for item in data.iter().cloned() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+async fn transfer_borrow<const CHECK: bool, T>(data: Vec<T>) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for item in &data {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_transfer_modes<T: Clone>(
+    group: &mut BenchmarkGroup<'_, WallTime>,
+    rt: &Runtime,
+    type_name: &str,
+    make: fn(i32) -> T,
+) {
+    let inputs: [(&str, Vec<T>); 2] = [("empty", vec![]), ("8 items", (0..8).map(make).collect())];
+
+    for (label, data) in &inputs {
+        let id = format!("{type_name}/{label}");
+        group.bench_function(BenchmarkId::new("move/with_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_move::<true, T>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("move/no_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_move::<false, T>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("clone/with_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_clone::<true, T>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("clone/no_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_clone::<false, T>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("borrow/with_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_borrow::<true, T>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(BenchmarkId::new("borrow/no_check", &id), |b| {
+            b.to_async(rt).iter_batched(
+                || data.clone(),
+                transfer_borrow::<false, T>,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn bench_async_ownership_transfer(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("133. ownership transfer modes");
+
+    bench_transfer_modes(&mut group, &rt, "i32", |i| i);
+    bench_transfer_modes(&mut group, &rt, "String", |i| format!("item-{i}"));
+    bench_transfer_modes(&mut group, &rt, "Vec<u8>", |i| vec![i as u8; 32]);
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 51: filter_map loop - Guarding on what survives the filter
+// `is_empty()` on the source only answers whether there is input, not
+// whether any of it passes the predicate, so a non-empty Vec whose items are
+// all filtered out still builds the loop future and runs the whole
+// `filter_map` inside it. An `iter().any(..)` pre-scan with the same
+// predicate answers the second question, at the price of reading the input
+// once more: up to the first match when something passes, all of it when
+// nothing does. It pays when inputs are often fully filtered and the
+// predicate is cheap, which makes the scan cheaper than an unneeded future;
+// when most inputs have matches it is a second pass over the prefix.
+// Measured with 8 items: the pre-scan does not pay here. On all-filtered
+// input the unguarded loop never suspends and finishes in ~6 ns, while the
+// `any` scan takes ~9; it needs longer inputs or a costlier loop setup.
+// ============================================================
+
+fn keep_positive(&x: &i32) -> Option<i32> {
+    if x > 0 { Some(x) } else { None }
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn filter_map_with_check(data: &Vec<i32>) {
+    if data.is_empty() {
+        return;
+    }
+    for item in data.iter().filter_map(keep_positive) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn filter_map_any_check(data: &Vec<i32>) {
+    if !data.iter().any(|x| keep_positive(x).is_some()) {
+        return;
+    }
+    for item in data.iter().filter_map(keep_positive) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn filter_map_no_check(data: &Vec<i32>) {
+    for item in data.iter().filter_map(keep_positive) {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_filter_map_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 3] = [
+        ("empty", vec![]),
+        ("all filtered out", (-8..0).collect()),
+        ("half filtered", (-4..4).collect()),
+    ];
+
+    let mut group = c.benchmark_group("51. filter_map loop");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| filter_map_no_check(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("is_empty check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| filter_map_with_check(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("any check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| filter_map_any_check(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_split_processing: split_process over [0, 10, 100] items
+// In every run of ten items the first `fast_percent / 10` go to the fast
+// lane. Fast items are a multiply; slow ones take a scheduler round-trip.
+// The no_check variant partitions and starts both lanes regardless of what
+// they got.
+// Measured: an empty batch costs ~8 ns guarded against ~50 ns unguarded
+// (the partition and the `join!` of two lanes); with items the lane
+// guards are within noise.
+// ============================================================
+
+struct WorkItem {
+    value: i32,
+    fast: bool,
+}
+
+impl Categorizable for WorkItem {
+    fn is_fast(&self) -> bool {
+        self.fast
+    }
+}
+
+struct Multiply;
+
+impl FastHandler<WorkItem> for Multiply {
+    fn handle(&self, item: WorkItem) {
+        black_box(item.value.wrapping_mul(31));
+    }
+}
+
+struct RoundTrip;
+
+impl SlowHandler<WorkItem> for RoundTrip {
+    async fn handle(&self, item: WorkItem) {
+        tokio::task::yield_now().await;
+        black_box(item.value);
+    }
+}
+
+fn work_items(count: i32, fast_percent: i32) -> Vec<WorkItem> {
+    (0..count)
+        .map(|value| WorkItem {
+            value,
+            fast: value % 10 < fast_percent / 10,
+        })
+        .collect()
+}
+
+async fn split_process_no_check(items: Vec<WorkItem>) {
+    let (fast, slow): (Vec<_>, Vec<_>) = items.into_iter().partition(WorkItem::is_fast);
+    let fast_lane = async {
+        for item in fast {
+            Multiply.handle(item);
+            tokio::task::coop::consume_budget().await;
+        }
+    };
+    let slow_lane = async {
+        for item in slow {
+            RoundTrip.handle(item).await;
+        }
+    };
+    futures::join!(fast_lane, slow_lane);
+}
+
+fn bench_async_split_processing(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_split_processing: split_process");
+
+    for count in [0, 10, 100] {
+        for fast_percent in [0, 50, 90] {
+            let id = format!("{count} items/{fast_percent}% fast");
+            group.bench_function(BenchmarkId::new("with_check", &id), |b| {
+                b.to_async(&rt).iter_batched(
+                    || work_items(count, fast_percent),
+                    |items| split_process(items, &Multiply, &RoundTrip),
+                    BatchSize::SmallInput,
+                )
+            });
+            group.bench_function(BenchmarkId::new("no_check", &id), |b| {
+                b.to_async(&rt).iter_batched(
+                    || work_items(count, fast_percent),
+                    split_process_no_check,
+                    BatchSize::SmallInput,
+                )
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 42: Batched loop - One await per chunk instead of per item
+// Batch inserts trade per-item round-trips for one round-trip per chunk.
+// For empty input `chunks()` only asserts a non-zero size and builds a
+// two-word iterator whose first `next()` returns `None`, so what the guard
+// saves is the same future build-and-poll as elsewhere, not the chunking.
+// With 256 items the number of awaits is 256 / batch_size, and the sweep
+// shows how quickly that stops mattering next to the per-item work inside
+// a chunk: past the point where one round-trip is small against a chunk's
+// work, larger batches only add latency before the first result (and, for
+// a real database, larger statements and longer-held locks).
+// Measured: 256 items take ~6.2 µs at batch 1, ~0.95 µs at 8 and ~0.12 µs
+// at 64, almost exactly the await count; the guard saves ~1 ns on empty.
+// ============================================================
+
+async fn batch_async_work(chunk: &[i32]) {
+    tokio::task::yield_now().await;
+    black_box(chunk.iter().sum::<i32>());
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn batched_loop_with_check(data: &Vec<i32>, batch_size: usize) {
+    if data.is_empty() {
+        return;
+    }
+    for chunk in data.chunks(batch_size) {
+        batch_async_work(chunk).await;
+    }
+}
+
+#[allow(clippy::ptr_arg)] // `&Vec` like the mir_demo cases
+async fn batched_loop_no_check(data: &Vec<i32>, batch_size: usize) {
+    for chunk in data.chunks(batch_size) {
+        batch_async_work(chunk).await;
+    }
+}
+
+fn bench_batched_loop(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("256 items", (0..256).collect())];
+
+    let mut group = c.benchmark_group("42. Batched loop");
+
+    for batch_size in [1, 8, 64] {
+        for (label, data) in &inputs {
+            let id = format!("batch={batch_size}/{label}");
+            group.bench_with_input(BenchmarkId::new("with_check", &id), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| batched_loop_with_check(black_box(d), batch_size))
+            });
+            group.bench_with_input(BenchmarkId::new("no_check", &id), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| batched_loop_no_check(black_box(d), batch_size))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 134: profiled overhead - Per-sample variance across scenarios
+// This group runs under its own Criterion config with `JsonProfiler`
+// attached. Criterion calls a profiler only in `--profile-time` mode,
+// where it iterates each benchmark for a fixed time instead of sampling;
+// the profiler then writes the benchmark id and the profiled wall time to
+// `<benchmark>/profile/profile.json`. The per-sample timings
+// come from a normal run: Criterion stores each sample's iteration count
+// and total time in `new/sample.json`, and `scripts/sample_cv.py` turns
+// those into a coefficient of variation per scenario. A high CV marks a
+// case whose samples disagree, which for these loops usually means the
+// scheduler or the OS interrupted some samples and not others.
+//   cargo bench --bench async_loops -- 134
+//   python3 scripts/sample_cv.py target/criterion
+//   cargo bench --bench async_loops -- 134 --profile-time 5
+// ============================================================
+
+/// Writes one JSON object per profiled benchmark to its `profile.json`.
+struct JsonProfiler {
+    started: Option<Instant>,
+}
+
+impl Profiler for JsonProfiler {
+    fn start_profiling(&mut self, _benchmark_id: &str, _benchmark_dir: &Path) {
+        self.started = Some(Instant::now());
+    }
+
+    fn stop_profiling(&mut self, benchmark_id: &str, benchmark_dir: &Path) {
+        let Some(started) = self.started.take() else {
+            return;
+        };
+        let elapsed = started.elapsed().as_nanos();
+        std::fs::create_dir_all(benchmark_dir).unwrap();
+        // `{:?}` escapes quotes and backslashes the way JSON strings need.
+        let json = format!("{{\"benchmark\": {benchmark_id:?}, \"profiled_ns\": {elapsed}}}\n");
+        std::fs::write(benchmark_dir.join("profile.json"), json).unwrap();
+    }
+}
+
+fn bench_async_overhead_profiled(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("134. profiled overhead");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("yield/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    async_loop_with_await(data.clone()).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("yield/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| async_loop_with_await(black_box(d).clone()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("spawned/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| spawned_loop::<true>(black_box(d).clone()))
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("spawned/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| spawned_loop::<false>(black_box(d).clone()))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 43: Cancellation latency - Cancelling a loop at depth k
+// A task racing its loop against `CancellationToken::cancelled()` stops by
+// dropping the loop future wherever it is suspended. Each case advances a
+// 32-item `async_loop_with_await` to item k in setup, then times
+// `cancel()`, the `cancelled()` future observing it, and the drop. The loop
+// holds the same locals (the `IntoIter` and a finished `YieldNow`) at every
+// depth, so the drop cost should not grow with k: only the remaining items'
+// buffer is freed, and `i32` has no per-item drop. The never-started case
+// is the guarded call on empty input, where there is no loop future to drop.
+// Measured: `cancel()` plus observing it is ~160 ns (the token's lock and
+// waiter list, and freeing the token); the drop adds a flat ~45 ns at every
+// depth from 0 to 16.
+// ============================================================
+
+fn bench_cancellation_latency(c: &mut Criterion) {
+    let waker = noop_waker();
+    let data: Vec<i32> = (0..32).collect();
+
+    let mut group = c.benchmark_group("43. Cancellation latency");
+
+    group.bench_function("never started (with_check, empty)", |b| {
+        b.iter_batched(
+            || (CancellationToken::new(), Vec::<i32>::new()),
+            |(token, data)| {
+                token.cancel();
+                assert!(poll_once(token.cancelled(), &mut Context::from_waker(&waker)).is_ready());
+                if !data.is_empty() {
+                    mem::drop(async_loop_with_await(data));
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    for depth in [0, 1, 4, 16] {
+        group.bench_function(BenchmarkId::new("cancelled at item", depth), |b| {
+            b.iter_batched(
+                || {
+                    (
+                        CancellationToken::new(),
+                        polled_loop(data.clone(), depth + 1),
+                    )
+                },
+                |(token, fut)| {
+                    token.cancel();
+                    assert!(
+                        poll_once(token.cancelled(), &mut Context::from_waker(&waker)).is_ready()
+                    );
+                    mem::drop(fut);
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_work_list: process_work_list over [0, 10, 100] initial items
+// Each item carries the generations left below it; analysing an item with
+// generations left yields `fan-out` children one generation down, so every
+// initial item expands into 1 + f + f^2 analyses over two generations. Each
+// analysis takes one scheduler round-trip. The total analysed per case is
+// printed before the group runs.
+// ============================================================
+
+async fn analyze_item(generations_left: u32, fan_out: usize) -> Vec<u32> {
+    tokio::task::yield_now().await;
+    match generations_left {
+        0 => Vec::new(),
+        n => vec![n - 1; fan_out],
+    }
+}
+
+fn bench_async_work_list(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let cases: Vec<(usize, usize)> = [0, 10, 100]
+        .into_iter()
+        .flat_map(|initial| [0, 1, 2].map(|fan_out| (initial, fan_out)))
+        .collect();
+
+    for &(initial, fan_out) in &cases {
+        let mut work_list = VecDeque::from(vec![2u32; initial]);
+        let processed = rt.block_on(process_work_list(&mut work_list, |g| {
+            analyze_item(g, fan_out)
+        }));
+        println!("{initial} initial/fan-out {fan_out}: {processed} analysed");
+    }
+
+    let mut group = c.benchmark_group("async_work_list: process_work_list");
+
+    for (initial, fan_out) in cases {
+        let id = format!("{initial} initial/fan-out {fan_out}");
+        group.bench_function(id, |b| {
+            b.to_async(&rt).iter_batched(
+                || VecDeque::from(vec![2u32; initial]),
+                |mut work_list| async move {
+                    process_work_list(&mut work_list, |g| analyze_item(g, fan_out)).await
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 53: Multi-runtime - The same loops on two independent runtimes
+// Two `Runtime::new()` instances stand in for services that each built
+// their own runtime around a shared library. In the "both runtimes" cases
+// one scoped thread per runtime `block_on`s the same `iters` calls, and the
+// item counts the two report must match. The "forwarded" cases split one
+// loop across them: a task on the second runtime sends the items over an
+// mpsc channel, and the receiving loop on the first is woken from the
+// second's worker thread, so every wake-up crosses runtimes. That is only
+// possible because `Waker` is `Send + Sync`. The guard skips spawning the
+// sender and building the channel when there is nothing to send.
+// Measured: side by side, the two runtimes cost about what one does (the
+// loops share nothing); forwarding costs ~3 µs per 8-item loop, and on
+// empty input the guard avoids ~3.4 µs of spawn and channel setup.
+// ============================================================
+
+async fn counted_loop<const CHECK: bool>(data: Vec<i32>) -> usize {
+    if CHECK && data.is_empty() {
+        return 0;
+    }
+    let mut count = 0;
+    for item in data {
+        tokio::task::yield_now().await;
+        black_box(item);
+        count += 1;
+    }
+    count
+}
+
+fn run_on_both<F, Fut>(runtimes: &[Runtime; 2], iters: u64, make: F) -> Duration
+where
+    F: Fn() -> Fut + Sync,
+    Fut: Future<Output = usize>,
+{
+    let start = Instant::now();
+    let counts = std::thread::scope(|s| {
+        let handles = runtimes.each_ref().map(|rt| {
+            let make = &make;
+            s.spawn(move || {
+                rt.block_on(async {
+                    let mut total = 0;
+                    for _ in 0..iters {
+                        total += make().await;
+                    }
+                    total
+                })
+            })
+        });
+        handles.map(|h| h.join().unwrap())
+    });
+    let elapsed = start.elapsed();
+    assert_eq!(
+        counts[0], counts[1],
+        "both runtimes must see the same result"
+    );
+    elapsed
+}
+
+async fn forwarded_loop<const CHECK: bool>(sender_rt: &Runtime, data: &[i32]) -> usize {
+    if CHECK && data.is_empty() {
+        return 0;
+    }
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let items = data.to_vec();
+    sender_rt.spawn(async move {
+        for item in items {
+            tokio::task::yield_now().await;
+            tx.send(item).unwrap();
+        }
+    });
+    let mut count = 0;
+    while let Some(item) = rx.recv().await {
+        black_box(item);
+        count += 1;
+    }
+    count
+}
+
+fn bench_multi_runtime(c: &mut Criterion) {
+    let runtimes = [Runtime::new().unwrap(), Runtime::new().unwrap()];
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("53. Multi-runtime");
+
+    for (label, data) in &inputs {
+        group.bench_function(BenchmarkId::new("both runtimes/with_check", label), |b| {
+            b.iter_custom(|iters| {
+                run_on_both(&runtimes, iters, || counted_loop::<true>(data.clone()))
+            })
+        });
+        group.bench_function(BenchmarkId::new("both runtimes/no_check", label), |b| {
+            b.iter_custom(|iters| {
+                run_on_both(&runtimes, iters, || counted_loop::<false>(data.clone()))
+            })
+        });
+        group.bench_function(BenchmarkId::new("forwarded/with_check", label), |b| {
+            b.to_async(&runtimes[0])
+                .iter(|| forwarded_loop::<true>(&runtimes[1], black_box(data)))
+        });
+        group.bench_function(BenchmarkId::new("forwarded/no_check", label), |b| {
+            b.to_async(&runtimes[0])
+                .iter(|| forwarded_loop::<false>(&runtimes[1], black_box(data)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 135: HTTP middleware - Per-request middleware chains in hyper
+// A hyper HTTP/1 server and client talk over an in-memory duplex pipe, so
+// each iteration is one full request/response round-trip without sockets.
+// The server runs the route's middleware layers before its handler; each
+// layer is a boxed future that inspects the headers. With the guard, a
+// route with no layers goes straight to the handler, the same path as the
+// "direct handler" service that has no middleware code at all. The pipe,
+// the HTTP framing and the task wake-ups dominate every case, so even ten
+// layers are a small share of the round-trip.
+// Measured: direct handler and both 0-layer routes all land at ~11-11.7 us,
+// inside each other's noise, so the guard does match the direct path; but
+// so does no_check with zero layers, since an empty `for` costs nothing
+// next to the round-trip. Layers add roughly 0.2-0.5 us each (~13-16 us at
+// 10), with run-to-run spread of ~1 us on this 1-CPU box.
+// ============================================================
+
+trait Middleware: Send + Sync {
+    fn handle<'a>(&'a self, headers: &'a hyper::HeaderMap) -> BoxFuture<'a, ()>;
+}
+
+struct HeaderCount;
+
+impl Middleware for HeaderCount {
+    fn handle<'a>(&'a self, headers: &'a hyper::HeaderMap) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            black_box(headers.len());
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ServerMode {
+    Direct,
+    WithCheck,
+    NoCheck,
+}
+
+struct MiddlewareServer {
+    layers: Vec<Box<dyn Middleware>>,
+    mode: ServerMode,
+}
+
+impl MiddlewareServer {
+    async fn serve(
+        &self,
+        req: hyper::Request<hyper::body::Incoming>,
+    ) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+        match self.mode {
+            ServerMode::Direct => {}
+            ServerMode::WithCheck if self.layers.is_empty() => {}
+            ServerMode::WithCheck | ServerMode::NoCheck => {
+                for layer in &self.layers {
+                    layer.handle(req.headers()).await;
+                }
+            }
+        }
+        Ok(hyper::Response::new(Full::new(Bytes::from_static(b"ok"))))
+    }
+}
+
+/// Starts `server` on one end of an in-memory pipe and returns a client
+/// connected to the other end.
+async fn connect_middleware_server(
+    server: MiddlewareServer,
+) -> hyper::client::conn::http1::SendRequest<Empty<Bytes>> {
+    let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+    let server = Arc::new(server);
+    let service = hyper::service::service_fn(move |req| {
+        let server = Arc::clone(&server);
+        async move { server.serve(req).await }
+    });
+    tokio::spawn(
+        hyper::server::conn::http1::Builder::new()
+            .serve_connection(TokioIo::new(server_io), service),
+    );
+    let (sender, conn) = hyper::client::conn::http1::handshake(TokioIo::new(client_io))
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+    sender
+}
+
+async fn http_round_trip(sender: &mut hyper::client::conn::http1::SendRequest<Empty<Bytes>>) {
+    sender.ready().await.unwrap();
+    let req = hyper::Request::get("/").body(Empty::new()).unwrap();
+    let res = sender.send_request(req).await.unwrap();
+    black_box(res.into_body().collect().await.unwrap().to_bytes());
+}
+
+fn bench_async_http_server_middleware(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("135. HTTP middleware");
+
+    let mut cases = vec![("direct handler".to_string(), 0, ServerMode::Direct)];
+    for layers in [0, 3, 10] {
+        cases.push((
+            format!("with_check/{layers} layers"),
+            layers,
+            ServerMode::WithCheck,
+        ));
+        cases.push((
+            format!("no_check/{layers} layers"),
+            layers,
+            ServerMode::NoCheck,
+        ));
+    }
+
+    for (name, layers, mode) in cases {
+        let server = MiddlewareServer {
+            layers: (0..layers)
+                .map(|_| Box::new(HeaderCount) as Box<dyn Middleware>)
+                .collect(),
+            mode,
+        };
+        let mut sender = rt.block_on(connect_middleware_server(server));
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                rt.block_on(async {
+                    let start = Instant::now();
+                    for _ in 0..iters {
+                        http_round_trip(&mut sender).await;
+                    }
+                    start.elapsed()
+                })
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 44: Cow<[i32]> - Borrowing the input instead of cloning it
+// `async_loop_cow` takes `Cow<'_, [i32]>`: the borrowed form moves a slice
+// reference into the future, while the owned form carries a cloned `Vec`
+// just as `data.clone()` does for `async_loop_with_await`. Cloning an empty
+// `Vec` does not allocate even when the source has spare capacity, because
+// `Vec::clone` sizes the copy by `len`. So for empty input, `Borrowed(&[])`
+// and `Owned(vec![])` cost about the same, and the guard matters more than
+// the representation. Calling `Cow::into_owned()` inside the loop body
+// would bring back the clone the borrow avoided, and it would be paid on
+// every call for borrowed input. Keep it out of the hot path and only
+// convert when the data really has to outlive the borrow.
+// Measured: empty input costs ~2.3 ns guarded and ~11.5 ns unguarded in
+// both forms, so borrowing saves nothing there. On 8 items, borrowing
+// saves the clone: ~226 ns vs ~249 ns guarded.
+// ============================================================
+
+async fn async_loop_cow(data: Cow<'_, [i32]>) {
+    for &item in data.iter() {
+        tokio::task::yield_now().await;
+        black_box(item);
+    }
+}
+
+fn bench_cow_slice(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    // The empty input keeps spare capacity, so a clone would allocate if it
+    // copied capacity rather than length.
+    let inputs: [(&str, Vec<i32>); 2] = [
+        ("empty", Vec::with_capacity(64)),
+        ("8 items", (0..8).collect()),
+    ];
+
+    let mut group = c.benchmark_group("44. Cow<[i32]>");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(
+            BenchmarkId::new("borrowed/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        async_loop_cow(Cow::Borrowed(data)).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("borrowed/no_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| async_loop_cow(Cow::Borrowed(black_box(d))))
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("owned/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    async_loop_cow(Cow::Owned(data.clone())).await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("owned/no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| async_loop_cow(Cow::Owned(black_box(d).clone())))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_two_phase_commit: two_phase_commit over [0, 3, 5, 9] participants
+// With no participants the guard returns before either phase starts, so a
+// local transaction costs a length check. Every participant votes yes, so
+// each one costs two scheduler round-trips, one per phase, and no abort
+// runs.
+// Measured: 0 participants cost ~5 ns guarded vs ~7 ns unguarded, so a
+// local transaction is close to free either way; the guard saves only the
+// two empty loops. With participants, the round-trips dominate (~55 ns per
+// participant), and the guarded and unguarded runs swap order from run to
+// run.
+// ============================================================
+
+/// A participant whose prepare and commit each take one scheduler round-trip.
+struct LocalParticipant;
+
+impl Participant for LocalParticipant {
+    async fn prepare(&self, tx: u64) -> Result<(), String> {
+        tokio::task::yield_now().await;
+        black_box(tx);
+        Ok(())
+    }
+
+    async fn commit(&self, tx: u64) -> Result<(), String> {
+        tokio::task::yield_now().await;
+        black_box(tx);
+        Ok(())
+    }
+
+    async fn abort(&self, tx: u64) {
+        black_box(tx);
+    }
+}
+
+async fn two_phase_commit_no_check(
+    tx: u64,
+    participants: &[LocalParticipant],
+) -> Result<(), TwoPhaseError> {
+    for (index, participant) in participants.iter().enumerate() {
+        if let Err(reason) = participant.prepare(tx).await {
+            for prepared in &participants[..index] {
+                prepared.abort(tx).await;
+            }
+            return Err(TwoPhaseError::Aborted { index, reason });
+        }
+    }
+    for (index, participant) in participants.iter().enumerate() {
+        participant
+            .commit(tx)
+            .await
+            .map_err(|reason| TwoPhaseError::CommitFailed { index, reason })?;
+    }
+    Ok(())
+}
+
+fn bench_async_two_phase_commit(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("async_two_phase_commit: two_phase_commit");
+
+    for count in [0, 3, 5, 9] {
+        let participants: Vec<LocalParticipant> = (0..count).map(|_| LocalParticipant).collect();
+        group.bench_with_input(
+            BenchmarkId::new("with_check", count),
+            &participants,
+            |b, p| {
+                b.to_async(&rt)
+                    .iter(|| two_phase_commit(black_box(1), black_box(p)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("no_check", count),
+            &participants,
+            |b, p| {
+                b.to_async(&rt)
+                    .iter(|| two_phase_commit_no_check(black_box(1), black_box(p)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 45: Stack vs heap pin - `pin!` instead of `Box::pin`
+// `Box::pin` moves the loop future into a fresh heap allocation so it can
+// be polled through `Pin<Box<_>>`; `pin!` pins it in place, in the calling
+// future's own state, with no allocation. The heap cost is isolated in
+// Scenario 123 (Scenario 2 is the simulated-I/O group). Under the guard,
+// neither pin is reached for empty input. Stack pinning is possible when
+// the future only has to live for the enclosing scope and is awaited there,
+// and when its concrete type is known. A future that has to be stored in a
+// struct, returned, or kept next to others behind `dyn Future` needs the
+// `Box`.
+// Measured: unguarded on empty input, stack is ~9 ns and heap ~28 ns, so
+// the allocation is ~19 ns; guarded, both are ~2.5 ns. On 8 items all four
+// land at ~245-260 ns, where the one allocation is lost in the yields.
+// ============================================================
+
+fn bench_stack_vs_heap_pin(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("45. Stack vs heap pin");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("stack/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    let fut = pin!(async_loop_with_await(data.clone()));
+                    fut.await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("stack/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let fut = pin!(async_loop_with_await(black_box(d).clone()));
+                fut.await;
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("heap/with_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let data = black_box(d);
+                if !data.is_empty() {
+                    let fut = Box::pin(async_loop_with_await(data.clone()));
+                    fut.await;
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("heap/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| async {
+                let fut = Box::pin(async_loop_with_await(black_box(d).clone()));
+                fut.await;
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 136: complex state machine - Guard placement in a multi-loop fn
+// `order_flow` walks the same data three times, like a validate / price /
+// publish flow: a loop that awaits per item, a purely synchronous
+// reduction, then a second awaiting loop. Its state machine has one
+// suspend variant per awaiting loop. A single guard at the top returns
+// before any loop is set up. A guard before each loop gives the same fast
+// path but checks the length three times. With no guard, all three
+// iterators are created and probed. Since all three loops walk the same
+// Vec, the length cannot change between them, so one guard at the top is
+// enough. Per-loop guards only pay off when each loop has its own
+// collection that may be empty on its own.
+// Measured: on empty data both guarded forms cost ~4.2 ns against ~6.6 ns
+// with no guards; the extra length checks in "per loop" do not show. On 8
+// items all three land at ~400-420 ns, dominated by the 16 yields.
+// ============================================================
+
+#[derive(Clone, Copy)]
+enum GuardPlacement {
+    Top,
+    EachLoop,
+    None,
+}
+
+async fn order_flow(data: &[i32], guards: GuardPlacement) -> i64 {
+    if matches!(guards, GuardPlacement::Top) && data.is_empty() {
+        return 0;
+    }
+    let each = matches!(guards, GuardPlacement::EachLoop);
+
+    let mut valid = 0i64;
+    if !each || !data.is_empty() {
+        for &item in data {
+            tokio::task::yield_now().await;
+            valid += i64::from(item >= 0);
+        }
+    }
+
+    let mut total = 0i64;
+    if !each || !data.is_empty() {
+        for &item in data {
+            total += i64::from(black_box(item)) * 3;
+        }
+    }
+
+    if !each || !data.is_empty() {
+        for &item in data {
+            tokio::task::yield_now().await;
+            black_box(item);
+        }
+    }
+    valid + total
+}
+
+fn bench_async_complex_state_machine(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+    let placements = [
+        ("guard at top", GuardPlacement::Top),
+        ("guard per loop", GuardPlacement::EachLoop),
+        ("no guards", GuardPlacement::None),
+    ];
+
+    let mut group = c.benchmark_group("136. complex state machine");
+
+    for (label, data) in &inputs {
+        for (name, guards) in placements {
+            group.bench_with_input(BenchmarkId::new(name, label), data, |b, d| {
+                b.to_async(&rt)
+                    .iter(|| order_flow(black_box(d), black_box(guards)))
+            });
+        }
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_token_bucket: consume_batch over [0, 1, 100] items
+// Every bucket starts with 10 local tokens and leases 1000 at a time from
+// a source whose grant takes one scheduler round-trip, so 1 item is paid
+// locally and 100 items need one lease. Buckets are built in the batch
+// setup, so each call sees the same starting balance. With the guard, an
+// empty batch returns before the balance is read; without it, it costs one
+// atomic read-modify-write that takes zero tokens.
+// Measured (each including the bucket's drop): 0 items cost ~17 ns guarded
+// vs ~39 ns unguarded, 1 item paid locally ~35 ns either way, and 100 items
+// with one lease ~90 ns, so the local path saves one remote round-trip and
+// the guard saves the atomic update on top of it.
+// ============================================================
+
+/// A token source that grants every lease after one scheduler round-trip.
+struct LocalTokenSource;
+
+impl AsyncTokenSource for LocalTokenSource {
+    fn acquire(&self, tokens: u64) -> BoxFuture<'_, Result<u64, String>> {
+        Box::pin(async move {
+            tokio::task::yield_now().await;
+            Ok(tokens)
+        })
+    }
+}
+
+fn bench_async_token_bucket(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let source: Arc<dyn AsyncTokenSource> = Arc::new(LocalTokenSource);
+    let bucket = || DistributedTokenBucket::new(Arc::clone(&source), 10, 1000);
+
+    let mut group = c.benchmark_group("async_token_bucket: consume_batch");
+
+    for items in [0usize, 1, 100] {
+        group.bench_with_input(BenchmarkId::new("with_check", items), &items, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                bucket,
+                |bucket| async move {
+                    bucket.consume_batch(black_box(n)).await.unwrap();
+                    bucket
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", items), &items, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                bucket,
+                |bucket| async move {
+                    bucket.consume_batch_no_check(black_box(n)).await.unwrap();
+                    bucket
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 46: Waker implementation - What building the waker costs
+// Scenario 31 builds each waker once, outside the timed loop. Here every
+// iteration builds one and polls an empty `with_check` / `no_check` loop
+// with it, the way a `block_on` would:
+//   stack        - a `StackWaker` on the stack: no allocation, no atomics
+//   futures noop - `futures::task::noop_waker`: a static vtable
+//   thread (Arc) - an `Arc<impl Wake>` that unparks the thread, the usual
+//                  hand-rolled `block_on` waker: one allocation per build
+//   tokio        - a current-thread runtime's `block_on`, whose waker is
+//                  built from tokio's cached thread parker
+// Neither loop touches its waker on empty input, so each case measures the
+// build and drop of the waker, plus the one poll.
+// Measured: stack and futures noop both run at ~0.4-0.5 ns, because the
+// whole build-poll-drop inlines away. thread (Arc) costs ~45 ns, which
+// covers `thread::current()` as well as the allocation. tokio costs
+// ~225-250 ns, most of it spent entering the runtime in `block_on` rather
+// than on the waker. Against any of these, the guard's difference is
+// within the noise.
+// ============================================================
+
+struct ThreadUnparker(std::thread::Thread);
+
+impl std::task::Wake for ThreadUnparker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn bench_waker_implementation(c: &mut Criterion) {
+    let empty_data: Vec<i32> = vec![];
+    let current = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("46. Waker implementation");
+
+    group.bench_function("with_check/stack", |b| {
+        b.iter(|| {
+            let stack = StackWaker::new();
+            // SAFETY: the loop is polled in place here and never wakes.
+            let waker = unsafe { stack.waker() };
+            poll_once(
+                with_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("no_check/stack", |b| {
+        b.iter(|| {
+            let stack = StackWaker::new();
+            // SAFETY: the loop is polled in place here and never wakes.
+            let waker = unsafe { stack.waker() };
+            poll_once(
+                no_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("with_check/futures noop", |b| {
+        b.iter(|| {
+            let waker = futures::task::noop_waker();
+            poll_once(
+                with_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("no_check/futures noop", |b| {
+        b.iter(|| {
+            let waker = futures::task::noop_waker();
+            poll_once(
+                no_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("with_check/thread (Arc)", |b| {
+        b.iter(|| {
+            let waker = Waker::from(Arc::new(ThreadUnparker(std::thread::current())));
+            poll_once(
+                with_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("no_check/thread (Arc)", |b| {
+        b.iter(|| {
+            let waker = Waker::from(Arc::new(ThreadUnparker(std::thread::current())));
+            poll_once(
+                no_check(black_box(&empty_data)),
+                &mut Context::from_waker(&waker),
+            )
+        })
+    });
+    group.bench_function("with_check/tokio", |b| {
+        b.iter(|| current.block_on(with_check(black_box(&empty_data))))
+    });
+    group.bench_function("no_check/tokio", |b| {
+        b.iter(|| current.block_on(no_check(black_box(&empty_data))))
+    });
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 137: panic overhead - Wrapping the loop in catch_unwind
+// `process_may_panic` panics on every 100th item (1%). The panic-safe
+// variants wrap the loop in `AssertUnwindSafe(..).catch_unwind()` from
+// `futures::FutureExt`, which runs every poll inside
+// `std::panic::catch_unwind`. A poll that does not panic only pays for the
+// extra call frame and the `Result` around the output; the unwind tables
+// cost nothing until a panic happens. The guard skips both the wrapper and
+// the loop for empty data. "99 items" never reaches a panicking item, so
+// both forms can run it. "100 items" panics on its last item and is only
+// run caught; it pays one full unwind per call. The default panic hook is
+// swapped for a silent one while the group runs, so the numbers do not
+// include printing a message per panic.
+// Measured: on empty data, the guard brings the caught form down from ~6.6
+// ns to ~1.9 ns, and the uncaught one from ~4.5 ns to ~2.7 ns; the wrapper
+// adds ~2 ns when unguarded. Over 99 items the wrapper is lost in the
+// yields (~2.4-3.2 µs with run-to-run order swaps). The one caught panic
+// in "100 items" adds ~3 µs, about as much as the 99 yields before it.
+// ============================================================
+
+async fn process_may_panic(item: i32) {
+    tokio::task::yield_now().await;
+    if item % 100 == 99 {
+        panic!("item {item} failed");
+    }
+    black_box(item);
+}
+
+async fn loop_may_panic(data: &[i32]) {
+    for &item in data {
+        process_may_panic(item).await;
+    }
+}
+
+async fn loop_caught(data: &[i32]) -> bool {
+    AssertUnwindSafe(loop_may_panic(data))
+        .catch_unwind()
+        .await
+        .is_ok()
+}
+
+fn bench_async_overhead_with_panics(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 3] = [
+        ("empty", vec![]),
+        ("99 items", (0..99).collect()),
+        ("100 items", (0..100).collect()),
+    ];
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+
+    let mut group = c.benchmark_group("137. panic overhead");
+
+    for (label, data) in &inputs {
+        let panics = data.iter().any(|item| item % 100 == 99);
+        group.bench_with_input(
+            BenchmarkId::new("caught/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        black_box(loop_caught(data).await);
+                    }
+                })
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("caught/no_check", label), data, |b, d| {
+            b.to_async(&rt).iter(|| loop_caught(black_box(d)))
+        });
+        if panics {
+            continue;
+        }
+        group.bench_with_input(
+            BenchmarkId::new("uncaught/with_check", label),
+            data,
+            |b, d| {
+                b.to_async(&rt).iter(|| async {
+                    let data = black_box(d);
+                    if !data.is_empty() {
+                        loop_may_panic(data).await;
+                    }
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("uncaught/no_check", label),
+            data,
+            |b, d| b.to_async(&rt).iter(|| loop_may_panic(black_box(d))),
+        );
+    }
+
+    group.finish();
+    std::panic::set_hook(default_hook);
+}
+
+// ============================================================
+// Scenario 47: Producer-consumer latency - Loop output to a waiting task
+// Each round spawns a producer that runs the loop, stores its results in a
+// shared `Vec` and calls `notify_one()`; the consumer, the benchmark's own
+// task, waits on `notified()` and takes the results. The timing is the
+// whole round from spawn to the consumer holding the output. `notify_one`
+// leaves a permit if the consumer is not waiting yet, so the order in
+// which the two tasks get scheduled does not matter. With the guard, an
+// empty producer stores nothing and notifies at once. Without it, the
+// producer first walks the empty loop.
+// Measured: an empty round takes ~3.5 µs with or without the guard. The
+// spawn and the consumer's wake-up are the latency, and the few ns of an
+// empty loop are lost in them. One item adds ~0.6-0.8 µs, which is the
+// producer's yield plus its trip back through the run queue.
+// ============================================================
+
+struct Handoff {
+    results: std::sync::Mutex<Vec<i32>>,
+    ready: Notify,
+}
+
+async fn produce<const CHECK: bool>(data: Vec<i32>, handoff: Arc<Handoff>) {
+    if !CHECK || !data.is_empty() {
+        let mut out = Vec::with_capacity(data.len());
+        for item in data {
+            tokio::task::yield_now().await;
+            out.push(item * 2);
+        }
+        handoff.results.lock().unwrap().extend(out);
+    }
+    handoff.ready.notify_one();
+}
+
+async fn producer_consumer_round<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    let handoff = Arc::new(Handoff {
+        results: std::sync::Mutex::new(Vec::new()),
+        ready: Notify::new(),
+    });
+    tokio::spawn(produce::<CHECK>(data, Arc::clone(&handoff)));
+    handoff.ready.notified().await;
+    std::mem::take(&mut *handoff.results.lock().unwrap())
+}
+
+fn bench_producer_consumer(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("1 item", vec![1])];
+
+    let mut group = c.benchmark_group("47. Producer-consumer latency");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("with_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| producer_consumer_round::<true>(black_box(d).clone()))
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| producer_consumer_round::<false>(black_box(d).clone()))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_version_vector: sync_vector_clock over [0, 3, 9] peers
+// Each peer has seen events from its own id and returns its clock after one
+// scheduler round-trip, a lower bound on a network exchange. The local
+// clock is rebuilt in the batch setup so it does not keep growing. With no
+// peers the guard returns before the loop; with peers, it is one length
+// check next to the exchanges.
+// Measured: 0 peers cost ~41 ns either way, mostly dropping the returned
+// clock's map. 3 and 9 peers take ~0.5 µs and ~1.4 µs, about 150 ns per
+// exchange and merge. The with_check/no_check order flips between them, so
+// the length check is below the noise.
+// ============================================================
+
+/// A peer whose exchange takes one scheduler round-trip.
+struct ClockPeer {
+    clock: VectorClock,
+}
+
+impl ClockPeer {
+    fn new(id: u64) -> Self {
+        let mut clock = VectorClock::new();
+        for _ in 0..id {
+            clock.increment(id);
+        }
+        Self { clock }
+    }
+}
+
+impl VersionVectorNode for ClockPeer {
+    async fn exchange(&self, clock: &VectorClock) -> Option<VectorClock> {
+        tokio::task::yield_now().await;
+        black_box(clock);
+        Some(self.clock.clone())
+    }
+}
+
+async fn sync_vector_clock_no_check(
+    peers: Vec<ClockPeer>,
+    local_clock: &mut VectorClock,
+) -> VectorSyncResult {
+    let mut result = VectorSyncResult::default();
+    for peer in &peers {
+        match peer.exchange(local_clock).await {
+            Some(remote) => {
+                local_clock.merge(&remote);
+                result.synced += 1;
+            }
+            None => result.unreachable += 1,
+        }
+    }
+    result
+}
+
+fn bench_async_version_vector(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let setup = |n: u64| {
+        let peers: Vec<ClockPeer> = (1..=n).map(ClockPeer::new).collect();
+        let mut local = VectorClock::new();
+        local.increment(0);
+        (peers, local)
+    };
+
+    let mut group = c.benchmark_group("async_version_vector: sync_vector_clock");
+
+    for count in [0u64, 3, 9] {
+        group.bench_with_input(BenchmarkId::new("with_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || setup(n),
+                |(peers, mut local)| async move {
+                    black_box(sync_vector_clock(peers, &mut local).await);
+                    local
+                },
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || setup(n),
+                |(peers, mut local)| async move {
+                    black_box(sync_vector_clock_no_check(peers, &mut local).await);
+                    local
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 48: Ordered vs Unordered - FuturesOrdered vs FuturesUnordered
+// Both collect one future per item and drain them as a stream.
+// `FuturesOrdered` wraps a `FuturesUnordered` and adds a sequence number
+// per future and a `BinaryHeap` (not a `VecDeque`) that holds outputs
+// finishing ahead of their turn, so results come out in input order.
+// `FuturesUnordered` yields them as they finish. Each item's future yields
+// once, so for both types every future is polled at least twice. Building
+// either set allocates even when empty: `FuturesUnordered::new` puts its
+// ready queue and that queue's stub task in two `Arc`s up front. The
+// guard skips both for empty input.
+//
+// Run with `--features alloc-tracking` to also print the allocator calls
+// per size.
+// Measured (with alloc-tracking on): empty sets cost 2 allocations and
+// ~150-190 ns unguarded against ~16-26 ns and none guarded. Ordered and
+// unordered allocate the same (8 at 4 items, 22 at 16), because these
+// futures finish in order and never spill into the heap. Ordered is ~10%
+// slower at 4 items and level at 16.
+// ============================================================
+
+async fn doubled_after_yield(item: i32) -> i32 {
+    tokio::task::yield_now().await;
+    item * 2
+}
+
+async fn loop_ordered<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    if CHECK && data.is_empty() {
+        return Vec::new();
+    }
+    let pending: FuturesOrdered<_> = data.into_iter().map(doubled_after_yield).collect();
+    pending.collect().await
+}
+
+async fn loop_unordered<const CHECK: bool>(data: Vec<i32>) -> Vec<i32> {
+    if CHECK && data.is_empty() {
+        return Vec::new();
+    }
+    let pending: FuturesUnordered<_> = data.into_iter().map(doubled_after_yield).collect();
+    pending.collect().await
+}
+
+fn bench_ordered_vs_unordered(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let sizes = [0, 4, 16];
+
+    #[cfg(feature = "alloc-tracking")]
+    for n in sizes {
+        let data: Vec<i32> = (0..n).collect();
+        println!(
+            "{n:>2} items allocs: ordered with_check {}, no_check {}; unordered with_check {}, no_check {}",
+            with_alloc_count(|| rt.block_on(loop_ordered::<true>(data.clone()))).1,
+            with_alloc_count(|| rt.block_on(loop_ordered::<false>(data.clone()))).1,
+            with_alloc_count(|| rt.block_on(loop_unordered::<true>(data.clone()))).1,
+            with_alloc_count(|| rt.block_on(loop_unordered::<false>(data.clone()))).1,
+        );
+    }
+
+    let mut group = c.benchmark_group("48. Ordered vs Unordered");
+
+    for n in sizes {
+        let data: Vec<i32> = (0..n).collect();
+        group.bench_with_input(BenchmarkId::new("ordered/with_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter_batched(|| d.clone(), loop_ordered::<true>, BatchSize::SmallInput)
+        });
+        group.bench_with_input(BenchmarkId::new("ordered/no_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter_batched(|| d.clone(), loop_ordered::<false>, BatchSize::SmallInput)
+        });
+        group.bench_with_input(
+            BenchmarkId::new("unordered/with_check", n),
+            &data,
+            |b, d| {
+                b.to_async(&rt).iter_batched(
+                    || d.clone(),
+                    loop_unordered::<true>,
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(BenchmarkId::new("unordered/no_check", n), &data, |b, d| {
+            b.to_async(&rt).iter_batched(
+                || d.clone(),
+                loop_unordered::<false>,
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 49: JoinSet vs FuturesUnordered - Spawned vs inline concurrency
+// `JoinSet::spawn` hands every future to the runtime as its own task, so the
+// future must be `Send + 'static`: it may run on any worker and can outlive
+// the caller's borrows. It cannot capture `&data` or hold an `Rc`.
+// `join_next()` then waits on a task's completion through tokio's queues
+// and wakers, even when the future was ready on its first poll.
+// `FuturesUnordered` keeps the futures inside the caller's own task and
+// polls them inline from `next()`. It takes borrowing and `!Send` futures,
+// but everything in it runs on one thread, so a CPU-heavy future stalls
+// the others. Use `JoinSet` for parallelism or tasks that must outlive the
+// caller, and `FuturesUnordered` for cheap fan-out. The guard skips
+// building the empty set: two `Arc`s for the unordered set's ready queue,
+// and one for the `JoinSet`'s task lists.
+// Measured: empty sets cost ~64 ns (JoinSet) and ~135 ns (unordered)
+// unguarded, against ~9-12 ns guarded. With items, the spawns dominate:
+// JoinSet takes ~4.5 µs for 1 item and ~23 µs for 8, against ~0.45 µs and
+// ~2.1 µs inline, about 10x.
+// ============================================================
+
+async fn loop_join_set<const CHECK: bool>(data: Vec<i32>) -> i32 {
+    if CHECK && data.is_empty() {
+        return 0;
+    }
+    let mut set = JoinSet::new();
+    for item in data {
+        set.spawn(doubled_after_yield(item));
+    }
+    let mut sum = 0;
+    while let Some(result) = set.join_next().await {
+        sum += result.unwrap();
+    }
+    sum
+}
+
+async fn loop_unordered_sum<const CHECK: bool>(data: Vec<i32>) -> i32 {
+    if CHECK && data.is_empty() {
+        return 0;
+    }
+    let mut pending: FuturesUnordered<_> = data.into_iter().map(doubled_after_yield).collect();
+    let mut sum = 0;
+    while let Some(doubled) = pending.next().await {
+        sum += doubled;
+    }
+    sum
+}
+
+fn bench_joinset_vs_unordered(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("49. JoinSet vs FuturesUnordered");
+
+    for n in [0, 1, 8] {
+        let data: Vec<i32> = (0..n).collect();
+        group.bench_with_input(BenchmarkId::new("JoinSet/with_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter_batched(|| d.clone(), loop_join_set::<true>, BatchSize::SmallInput)
+        });
+        group.bench_with_input(BenchmarkId::new("JoinSet/no_check", n), &data, |b, d| {
+            b.to_async(&rt).iter_batched(
+                || d.clone(),
+                loop_join_set::<false>,
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("FuturesUnordered/with_check", n),
+            &data,
+            |b, d| {
+                b.to_async(&rt).iter_batched(
+                    || d.clone(),
+                    loop_unordered_sum::<true>,
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("FuturesUnordered/no_check", n),
+            &data,
+            |b, d| {
+                b.to_async(&rt).iter_batched(
+                    || d.clone(),
+                    loop_unordered_sum::<false>,
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 54: Cold path hints - Marking the empty branch unlikely
+// When input is almost never empty, the guard's early return is the cold
+// branch. `with_check_likely_nonempty` says so with `std::hint::cold_path()`,
+// the stable form of `std::intrinsics::unlikely(data.is_empty())` (the
+// intrinsic is nightly-only). The hint changes block layout and branch
+// weights, not the instructions that run; the CPU's predictor learns a
+// branch that always goes one way within a few calls whatever the layout.
+// In a loop that keeps seeing the same input, any difference would come
+// from code placement, and it should be within the noise.
+// Measured over two runs: empty ~2.3-2.7 ns and 8 items ~13-17 ns for
+// both, and which one is faster flipped between the runs. The hint made no
+// difference that shows through the noise. (`async_work` is ready on the
+// first poll, so the 8-item loop never suspends.)
+// ============================================================
+
+async fn with_check_unhinted(data: &[i32]) {
+    if data.is_empty() {
+        return;
+    }
+    for &item in data {
+        async_work().await;
+        black_box(item);
+    }
+}
+
+async fn with_check_likely_nonempty(data: &[i32]) {
+    if data.is_empty() {
+        std::hint::cold_path();
+        return;
+    }
+    for &item in data {
+        async_work().await;
+        black_box(item);
+    }
+}
+
+fn bench_async_cold_path_optimization(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let inputs: [(&str, Vec<i32>); 2] = [("empty", vec![]), ("8 items", (0..8).collect())];
+
+    let mut group = c.benchmark_group("54. Cold path hints");
+
+    for (label, data) in &inputs {
+        group.bench_with_input(BenchmarkId::new("unhinted", label), data, |b, d| {
+            b.to_async(&rt).iter(|| with_check_unhinted(black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("cold_path", label), data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| with_check_likely_nonempty(black_box(d)))
+        });
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// async_segment_tree: process_range_queries over [0, 10, 1000] queries
+// The tree holds 1024 values. With a prebuilt tree, the guard on an empty
+// batch only skips the result `Vec` and the loop, so with_check and
+// no_check should sit close together. The setup worth skipping is the
+// build: the "build" cases include `SegmentTree::new` in the timing, once
+// with the caller checking the batch first and once without.
+// Measured: with a prebuilt tree, 0 queries cost ~16 ns guarded vs ~24 ns
+// unguarded. Building the tree costs ~1.4 µs, which the caller's check
+// avoids (~13 ns vs ~1.5 µs at 0). From 10 queries up, the build is paid
+// either way. 1000 queries take ~70-80 µs including the budget yields.
+// ============================================================
+
+struct SumQuery(Range<usize>);
+
+impl RangeQuery for SumQuery {
+    type Result = i64;
+
+    fn range(&self) -> Range<usize> {
+        self.0.clone()
+    }
+
+    fn answer(&self, sum: i64) -> i64 {
+        sum
+    }
+}
+
+async fn process_range_queries_no_check(queries: Vec<SumQuery>, tree: &SegmentTree) -> Vec<i64> {
+    let mut results = Vec::with_capacity(queries.len());
+    for query in &queries {
+        results.push(query.answer(tree.sum(query.range())));
+        tokio::task::coop::consume_budget().await;
+    }
+    results
+}
+
+fn range_queries(count: usize, len: usize) -> Vec<SumQuery> {
+    (0..count)
+        .map(|i| {
+            let start = i * 37 % len;
+            SumQuery(start..(start + i * 101 % len).min(len))
+        })
+        .collect()
+}
+
+fn bench_async_segment_tree(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let values: Vec<i64> = (0..1024).map(|v| v * 31 % 97).collect();
+    let tree = SegmentTree::new(&values);
+
+    let mut group = c.benchmark_group("async_segment_tree: process_range_queries");
+
+    for count in [0, 10, 1000] {
+        group.bench_with_input(BenchmarkId::new("with_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || range_queries(n, values.len()),
+                |queries| process_range_queries(queries, &tree),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("no_check", count), &count, |b, &n| {
+            b.to_async(&rt).iter_batched(
+                || range_queries(n, values.len()),
+                |queries| process_range_queries_no_check(queries, &tree),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("build/caller_check", count),
+            &count,
+            |b, &n| {
+                b.to_async(&rt).iter_batched(
+                    || range_queries(n, values.len()),
+                    |queries| async {
+                        if queries.is_empty() {
+                            return Vec::new();
+                        }
+                        let tree = SegmentTree::new(black_box(&values));
+                        process_range_queries(queries, &tree).await
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("build/no_check", count),
+            &count,
+            |b, &n| {
+                b.to_async(&rt).iter_batched(
+                    || range_queries(n, values.len()),
+                    |queries| async {
+                        let tree = SegmentTree::new(black_box(&values));
+                        process_range_queries(queries, &tree).await
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Scenario 50: async fn in trait - Native RPIT vs `async-trait` boxing
+// `async fn` in traits needs Rust 1.75 or later. This crate's edition 2024
+// already needs 1.85, so it adds no MSRV of its own. The "async_trait
+// overhead" group compares the two forms with a yielding handler. Here
+// `Worker::do_work` never suspends, which is the case where static dispatch
+// can do the most. The native method returns an anonymous `impl Future`
+// that the loop can inline into a plain loop over the items. The
+// `async-trait` version boxes a `dyn Future` per call and can only be
+// reached through the vtable. The trait object is what the box buys: a
+// native `async fn` makes the trait unusable as `dyn AsyncWork`. With no
+// items, neither form calls the method.
+// Measured: inlining did not remove the empty-loop cost. Unguarded, both
+// forms take ~5 ns at 0 items, against ~2.8 ns (native) and ~3.8 ns
+// (async-trait) with the guard. The box is what costs with items: ~6 vs
+// ~30 ns for 1 item, and ~34 vs ~445 ns for 16, about 25 ns per call.
+// ============================================================
+
+trait AsyncWork {
+    async fn do_work(&self, item: i32);
+}
+
+#[async_trait::async_trait]
+trait BoxedAsyncWork {
+    async fn do_work(&self, item: i32);
+}
+
+struct Worker;
+
+impl AsyncWork for Worker {
+    async fn do_work(&self, item: i32) {
+        black_box(item.wrapping_mul(3));
+    }
+}
+
+#[async_trait::async_trait]
+impl BoxedAsyncWork for Worker {
+    async fn do_work(&self, item: i32) {
+        black_box(item.wrapping_mul(3));
+    }
+}
+
+async fn work_loop<const CHECK: bool, W: AsyncWork>(worker: &W, data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        worker.do_work(item).await;
+    }
+}
+
+async fn boxed_work_loop<const CHECK: bool>(worker: &dyn BoxedAsyncWork, data: &[i32]) {
+    if CHECK && data.is_empty() {
+        return;
+    }
+    for &item in data {
+        worker.do_work(item).await;
+    }
+}
+
+fn bench_async_fn_in_trait(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let worker = Worker;
+    let boxed: &dyn BoxedAsyncWork = &worker;
+
+    let mut group = c.benchmark_group("50. async fn in trait");
+
+    for n in [0, 1, 16] {
+        let data: Vec<i32> = (0..n).collect();
+        group.bench_with_input(BenchmarkId::new("native/with_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| work_loop::<true, _>(&worker, black_box(d)))
+        });
+        group.bench_with_input(BenchmarkId::new("native/no_check", n), &data, |b, d| {
+            b.to_async(&rt)
+                .iter(|| work_loop::<false, _>(&worker, black_box(d)))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("async-trait/with_check", n),
+            &data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| boxed_work_loop::<true>(boxed, black_box(d)))
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("async-trait/no_check", n),
+            &data,
+            |b, d| {
+                b.to_async(&rt)
+                    .iter(|| boxed_work_loop::<false>(boxed, black_box(d)))
+            },
+        );
+    }
+
+    group.finish();
+}
+
+// ============================================================
+// Future sizes - State machine size next to each timing group
+// `mem::size_of_val(&fut)` is a compile-time constant, but surfacing it
+// through Criterion puts "bytes" next to "ns" for the same case names, so a
+// larger state machine can be correlated with more runtime overhead.
+// Each size group mirrors a timing group above; every iteration constructs
+// (and drops, unpolled) the future that the timing case would drive.
+// ============================================================
+
+/// A Criterion measurement whose values are future sizes in bytes.
+///
+/// Values are produced by `iter_custom` as `size * iters`; `start`/`end` are
+/// never used to derive them.
+struct FutureSizeMeasurement;
+
+impl Measurement for FutureSizeMeasurement {
+    type Intermediate = ();
+    type Value = u64;
+
+    fn start(&self) -> Self::Intermediate {}
+
+    fn end(&self, _i: Self::Intermediate) -> Self::Value {
+        0
+    }
+
+    fn add(&self, v1: &Self::Value, v2: &Self::Value) -> Self::Value {
+        v1 + v2
+    }
+
+    fn zero(&self) -> Self::Value {
+        0
+    }
+
+    fn to_f64(&self, value: &Self::Value) -> f64 {
+        *value as f64
+    }
+
+    fn formatter(&self) -> &dyn ValueFormatter {
+        &BytesFormatter
+    }
+}
+
+struct BytesFormatter;
+
+impl ValueFormatter for BytesFormatter {
+    fn scale_values(&self, _typical_value: f64, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+
+    fn scale_throughputs(
+        &self,
+        _typical_value: f64,
+        throughput: &Throughput,
+        values: &mut [f64],
+    ) -> &'static str {
+        let (per, unit) = match *throughput {
+            Throughput::Bytes(n) | Throughput::BytesDecimal(n) => (n, "B/byte"),
+            Throughput::Elements(n) => (n, "B/elem"),
+        };
+        for value in values {
+            *value /= per as f64;
+        }
+        unit
+    }
+
+    fn scale_for_machines(&self, _values: &mut [f64]) -> &'static str {
+        "B"
+    }
+}
+
+/// Registers `name` in `group`, reporting the size of the future `make` builds.
+fn record_future_size<F, Fut>(
+    group: &mut BenchmarkGroup<'_, FutureSizeMeasurement>,
+    name: impl Into<String>,
+    make: F,
+) where
+    F: Fn() -> Fut,
+    Fut: Future,
+{
+    let bytes = mem::size_of_val(&make()) as u64;
+    group.bench_function(name.into(), |b| {
+        b.iter_custom(|iters| {
+            for _ in 0..iters {
+                black_box(make());
+            }
+            bytes * iters
+        })
+    });
+}
+
+fn bench_future_sizes(c: &mut Criterion<FutureSizeMeasurement>) {
+    let empty_data: Vec<i32> = vec![];
+    let one_item: Vec<i32> = vec![1];
+    let mutex = Arc::new(Mutex::new(Vec::<i32>::new()));
+    let rwlock = Arc::new(RwLock::new(Vec::<i32>::new()));
+
+    let mut group = c.benchmark_group("1. CPU-bound (yield_now) (future size)");
+    record_future_size(&mut group, "with_check", || async {
+        let data = black_box(&empty_data);
+        if !data.is_empty() {
+            async_loop_with_await(data.clone()).await;
+        }
+    });
+    record_future_size(&mut group, "no_check", || async {
+        let data = black_box(&empty_data);
+        async_loop_with_await(data.clone()).await;
+    });
+    record_future_size(&mut group, "async_loop_with_await", || {
+        async_loop_with_await(Vec::new())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("2. Simulated IO (1µs sleep) (future size)");
+    record_future_size(&mut group, "with_check", || async {
+        let data = black_box(&one_item);
+        if !data.is_empty() {
+            async_loop_with_sleep(data.clone()).await;
+        }
+    });
+    record_future_size(&mut group, "no_check", || async {
+        let data = black_box(&one_item);
+        async_loop_with_sleep(data.clone()).await;
+    });
+    record_future_size(&mut group, "async_loop_with_sleep", || {
+        async_loop_with_sleep(Vec::new())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("116. latency percentiles (future size)");
+    for (name, input, check) in [
+        ("with_check (empty)", &empty_data, true),
+        ("no_check (empty)", &empty_data, false),
+        ("with_check (1 item)", &one_item, true),
+        ("no_check (1 item)", &one_item, false),
+    ] {
+        record_future_size(&mut group, name, || async move {
+            let data = black_box(input);
+            if !check || !data.is_empty() {
+                async_loop_with_await(data.clone()).await;
+            }
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("14. Mutex-protected loop (future size)");
+    record_future_size(&mut group, "drain-then-iterate/with_check", || {
+        async_loop_with_mutex::<true>(mutex.clone())
+    });
+    record_future_size(&mut group, "drain-then-iterate/no_check", || {
+        async_loop_with_mutex::<false>(mutex.clone())
+    });
+    record_future_size(&mut group, "hold-lock/with_check", || {
+        async_loop_holding_mutex::<true>(mutex.clone())
+    });
+    record_future_size(&mut group, "hold-lock/no_check", || {
+        async_loop_holding_mutex::<false>(mutex.clone())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("15. RwLock vs Mutex (future size)");
+    record_future_size(&mut group, "rwlock read/with_check", || {
+        async_loop_rwlock::<true>(rwlock.clone())
+    });
+    record_future_size(&mut group, "rwlock read/no_check", || {
+        async_loop_rwlock::<false>(rwlock.clone())
+    });
+    record_future_size(&mut group, "rwlock write/with_check", || {
+        async_loop_rwlock_drain::<true>(rwlock.clone())
+    });
+    record_future_size(&mut group, "rwlock write/no_check", || {
+        async_loop_rwlock_drain::<false>(rwlock.clone())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("16. Oneshot collection (future size)");
+    record_future_size(&mut group, "oneshot", || {
+        async_loop_oneshot_collect(Vec::new())
+    });
+    record_future_size(&mut group, "join_all", || {
+        async_loop_join_all_collect(Vec::new())
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("118. generational cleanup (future size)");
+    record_future_size(&mut group, "with_check", || {
+        run_generational_ticks::<true>(1)
+    });
+    record_future_size(&mut group, "no_check", || {
+        run_generational_ticks::<false>(1)
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("Intrusive list vs Vec (future size)");
+    let list = IntrusiveVec::<i32, 8>::new();
+    record_future_size(&mut group, "intrusive", || async_loop_borrowed(&list));
+    record_future_size(&mut group, "vec", || async_loop_borrowed(&empty_data));
+    group.finish();
+
+    let mut group = c.benchmark_group("async_consensus: quorum_write (future size)");
+    record_future_size(&mut group, "quorum_write", || {
+        quorum_write(Vec::<LocalNode>::new(), Data::default())
+    });
+    group.finish();
+
+    let router = AsyncRouter::<RoutedMessage>::new();
+    let mut group = c.benchmark_group("async_routing: route (future size)");
+    record_future_size(&mut group, "route", || router.route(RoutedMessage("")));
+    record_future_size(&mut group, "route_no_check", || {
+        route_no_check(&router, RoutedMessage(""))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_empty_check,
+    bench_async_overhead_percentiles,
+    bench_async_consensus,
+    bench_mutex_loop,
+    bench_rwlock_vs_mutex,
+    bench_async_intrusive_list,
+    bench_async_routing,
+    bench_oneshot_collection,
+    bench_async_generational_gc,
+    bench_result_loops,
+    bench_work_stealing_deque,
+    bench_async_loop_fusion,
+    bench_sharded_processor,
+    bench_async_trait_overhead,
+    bench_inner_future_dispatch,
+    bench_async_zero_copy,
+    bench_async_event_bus,
+    bench_runtime_scaling,
+    bench_async_state_sync,
+    bench_slice_vs_vec,
+    bench_cooperative_multitasking,
+    bench_channel_drain,
+    bench_spawn_blocking_loop,
+    bench_async_multiversion,
+    bench_cooperative_ownership,
+    bench_recursive_async_loop,
+    bench_async_pipeline_stages,
+    bench_broadcast_drain,
+    bench_async_actor,
+    bench_atomic_vs_is_empty,
+    bench_async_drain_ordering,
+    bench_iterator_adapter_chains,
+    bench_hash_map_loop,
+    bench_async_vs_alloc_isolation,
+    bench_vec_deque_loop,
+    bench_async_cleanup,
+    bench_async_parallel_map_vs_sequential,
+    bench_mpsc_drain,
+    bench_async_epoch,
+    bench_tracing_instrument,
+    bench_async_idempotent_ops,
+    bench_poll_fn_vs_async_fn,
+    bench_async_feature_flags,
+    bench_waker_overhead,
+    bench_async_histogram,
+    bench_unconstrained,
+    bench_async_cache_warming,
+    bench_async_rollup,
+    bench_drop_cost,
+    bench_async_memory_pressure_gc,
+    bench_select_scaling,
+    bench_preallocated_vec,
+    bench_async_interrupt_coalescing,
+    bench_seq_and_then,
+    bench_async_dag_executor,
+    bench_yield_frequency,
+    bench_async_vectorized_batch,
+    bench_runtime_topology,
+    bench_async_lsm_tree,
+    bench_chain_vs_loop,
+    bench_async_overhead_distribution,
+    bench_fixed_size_array,
+    bench_async_schema_migration,
+    bench_async_task_batching,
+    bench_arc_vec,
+    bench_flat_map_loop,
+    bench_async_prefetch_buffer,
+    bench_async_ownership_transfer,
+    bench_filter_map_loop,
+    bench_async_split_processing,
+    bench_batched_loop,
+    bench_cancellation_latency,
+    bench_async_work_list,
+    bench_multi_runtime,
+    bench_async_http_server_middleware,
+    bench_cow_slice,
+    bench_async_two_phase_commit,
+    bench_stack_vs_heap_pin,
+    bench_async_complex_state_machine,
+    bench_async_token_bucket,
+    bench_waker_implementation,
+    bench_async_overhead_with_panics,
+    bench_producer_consumer,
+    bench_async_version_vector,
+    bench_ordered_vs_unordered,
+    bench_joinset_vs_unordered,
+    bench_async_cold_path_optimization,
+    bench_async_segment_tree,
+    bench_async_fn_in_trait
+);
+
+criterion_group! {
+    name = size_benches;
+    config = Criterion::default()
+        .with_measurement(FutureSizeMeasurement)
+        .warm_up_time(Duration::from_millis(100))
+        .measurement_time(Duration::from_millis(500))
+        // Sizes are constant, so there is no distribution to plot.
+        .without_plots();
+    targets = bench_future_sizes
+}
+
+criterion_group! {
+    name = profiled_benches;
+    config = Criterion::default().with_profiler(JsonProfiler { started: None });
+    targets = bench_async_overhead_profiled
+}
+
+criterion_main!(benches, size_benches, profiled_benches);
@@ -0,0 +1,6 @@
+fn main() {
+    // Lets benchmark group 121 label its results with the profile's opt-level.
+    let opt_level = std::env::var("OPT_LEVEL").unwrap_or_else(|_| String::from("unknown"));
+    println!("cargo:rustc-env=ASYNC_LOOP_PERF_OPT_LEVEL={opt_level}");
+    println!("cargo:rerun-if-changed=build.rs");
+}
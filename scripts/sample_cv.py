@@ -0,0 +1,67 @@
+#!/usr/bin/env python3
+"""Coefficient of variation of Criterion's per-sample timings.
+
+Reads every `new/sample.json` under a Criterion output directory (default
+`target/criterion`), converts each sample to time per iteration, and prints
+one line per benchmark sorted by CV, highest first. Samples of a steady
+benchmark agree within a few percent; a high CV means some samples were
+slowed by something outside the code under test, such as scheduler jitter.
+
+    python3 scripts/sample_cv.py [target/criterion] [--threshold 0.05]
+"""
+
+import argparse
+import json
+import math
+from pathlib import Path
+
+
+def per_iteration_times(sample_file):
+    with open(sample_file) as f:
+        sample = json.load(f)
+    return [t / n for t, n in zip(sample["times"], sample["iters"]) if n > 0]
+
+
+def cv(values):
+    mean = sum(values) / len(values)
+    var = sum((v - mean) ** 2 for v in values) / (len(values) - 1)
+    return mean, math.sqrt(var) / mean
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("criterion_dir", nargs="?", default="target/criterion")
+    parser.add_argument(
+        "--threshold",
+        type=float,
+        default=0.05,
+        help="flag benchmarks whose CV exceeds this (default: 0.05)",
+    )
+    args = parser.parse_args()
+
+    root = Path(args.criterion_dir)
+    rows = []
+    for sample_file in root.glob("**/new/sample.json"):
+        # The "(future size)" groups record bytes, not time.
+        if "(future size)" in sample_file.as_posix():
+            continue
+        times = per_iteration_times(sample_file)
+        if len(times) < 2:
+            continue
+        mean, coeff = cv(times)
+        name = sample_file.parent.parent.relative_to(root).as_posix()
+        rows.append((coeff, mean, name))
+
+    if not rows:
+        raise SystemExit(f"no sample.json files under {root}")
+
+    rows.sort(reverse=True)
+    width = max(len(name) for _, _, name in rows)
+    print(f"{'benchmark':<{width}}  {'mean/iter':>12}  {'CV':>7}")
+    for coeff, mean, name in rows:
+        flag = "  high variance" if coeff > args.threshold else ""
+        print(f"{name:<{width}}  {mean:>10.2f}ns  {coeff:>6.1%}{flag}")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,74 @@
+//! A minimal actor: a mailbox of queued messages drained by one async loop.
+//!
+//! Actors are woken on a schedule whether or not anything was sent to them,
+//! so most passes find an empty mailbox. The guard makes those passes a
+//! single length check instead of a handler loop that never runs.
+
+use std::future::Future;
+
+/// Marker for types that can be delivered to an actor.
+pub trait Message {}
+
+/// Handles one message at a time on behalf of an actor.
+pub trait Handler<M: Message> {
+    fn handle(&self, msg: M) -> impl Future<Output = ()>;
+}
+
+/// Bookkeeping kept across mailbox passes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActorState {
+    pub processed: u64,
+    pub passes: u64,
+}
+
+pub struct Actor<M: Message> {
+    mailbox: Vec<M>,
+    state: ActorState,
+}
+
+impl<M: Message> Actor<M> {
+    pub fn new() -> Self {
+        Self {
+            mailbox: Vec::new(),
+            state: ActorState::default(),
+        }
+    }
+
+    /// Queues `msg` for the next mailbox pass.
+    pub fn send(&mut self, msg: M) {
+        self.mailbox.push(msg);
+    }
+
+    pub fn state(&self) -> ActorState {
+        self.state
+    }
+
+    /// Delivers every queued message to `handler` in the order it was sent.
+    pub async fn process_mailbox<H: Handler<M>>(&mut self, handler: &H) {
+        self.state.passes += 1;
+        if self.mailbox.is_empty() {
+            return;
+        }
+
+        for msg in self.mailbox.drain(..) {
+            handler.handle(msg).await;
+            self.state.processed += 1;
+        }
+    }
+
+    /// [`process_mailbox`](Self::process_mailbox) without the empty-mailbox
+    /// guard, for comparison in benchmarks.
+    pub async fn process_mailbox_no_check<H: Handler<M>>(&mut self, handler: &H) {
+        self.state.passes += 1;
+        for msg in self.mailbox.drain(..) {
+            handler.handle(msg).await;
+            self.state.processed += 1;
+        }
+    }
+}
+
+impl<M: Message> Default for Actor<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,141 @@
+//! An async cleanup registry: RAII for resources whose teardown must await.
+//!
+//! `Drop` cannot await, so resources that need an async shutdown (flushing a
+//! writer, closing a connection) register a cleanup and the owner awaits
+//! `run_all` before letting them go. Most scopes register nothing, and an
+//! empty registry returns without touching the trait objects.
+
+use std::collections::VecDeque;
+
+use futures::future::BoxFuture;
+
+/// Async teardown for one resource.
+pub trait AsyncCleanup: Send {
+    fn cleanup(&mut self) -> BoxFuture<'_, ()>;
+}
+
+pub struct CleanupRegistry {
+    cleanups: VecDeque<Box<dyn AsyncCleanup>>,
+}
+
+impl CleanupRegistry {
+    pub fn new() -> Self {
+        Self {
+            cleanups: VecDeque::new(),
+        }
+    }
+
+    pub fn register(&mut self, cleanup: Box<dyn AsyncCleanup>) {
+        self.cleanups.push_back(cleanup);
+    }
+
+    pub fn len(&self) -> usize {
+        self.cleanups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cleanups.is_empty()
+    }
+
+    /// Runs every registered cleanup in registration order.
+    ///
+    /// Cancel-safe: a cleanup is unregistered only after it completes, so if
+    /// this future is dropped part-way, the interrupted cleanup and every one
+    /// after it are still registered and the next `run_all` runs them.
+    pub async fn run_all(&mut self) {
+        if self.cleanups.is_empty() {
+            return;
+        }
+
+        while let Some(cleanup) = self.cleanups.front_mut() {
+            cleanup.cleanup().await;
+            self.cleanups.pop_front();
+        }
+    }
+}
+
+impl Default for CleanupRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::pin::pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+
+    /// Yields once, then records its id as finished.
+    struct Recorder {
+        id: usize,
+        finished: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl AsyncCleanup for Recorder {
+        fn cleanup(&mut self) -> BoxFuture<'_, ()> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                self.finished.lock().unwrap().push(self.id);
+            })
+        }
+    }
+
+    fn registry(count: usize) -> (CleanupRegistry, Arc<Mutex<Vec<usize>>>) {
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = CleanupRegistry::new();
+        for id in 0..count {
+            registry.register(Box::new(Recorder {
+                id,
+                finished: finished.clone(),
+            }));
+        }
+        (registry, finished)
+    }
+
+    #[test]
+    fn runs_cleanups_in_registration_order() {
+        let (mut registry, finished) = registry(3);
+        block_on(registry.run_all());
+        assert_eq!(*finished.lock().unwrap(), [0, 1, 2]);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn cancelled_run_keeps_unfinished_cleanups() {
+        let (mut registry, finished) = registry(3);
+        {
+            let mut run = pin!(registry.run_all());
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            // Cleanup 0 yields on its first poll; dropping `run` cancels it there.
+            assert_eq!(run.as_mut().poll(&mut cx), Poll::Pending);
+        }
+        assert!(finished.lock().unwrap().is_empty());
+        assert_eq!(registry.len(), 3);
+
+        block_on(registry.run_all());
+        assert_eq!(*finished.lock().unwrap(), [0, 1, 2]);
+        assert!(registry.is_empty());
+    }
+
+    #[test]
+    fn cancelled_run_does_not_repeat_finished_cleanups() {
+        let (mut registry, finished) = registry(3);
+        {
+            let mut run = pin!(registry.run_all());
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            // Each cleanup is pending once: the second poll finishes cleanup 0
+            // and leaves cleanup 1 pending.
+            for _ in 0..2 {
+                assert_eq!(run.as_mut().poll(&mut cx), Poll::Pending);
+            }
+        }
+        assert_eq!(*finished.lock().unwrap(), [0]);
+        assert_eq!(registry.len(), 2);
+
+        block_on(registry.run_all());
+        assert_eq!(*finished.lock().unwrap(), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Raft-like quorum replication driven by an async loop over peers.
+//!
+//! A write is committed once `nodes.len() / 2 + 1` nodes acknowledge it. With
+//! no peers there is nothing to replicate, so the empty-check returns before
+//! any per-node future or the `FuturesUnordered` set is created.
+
+use std::fmt;
+use std::future::Future;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+
+/// The payload replicated to every node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Data(pub Vec<u8>);
+
+/// A replica that can acknowledge a write.
+pub trait Node {
+    /// Sends `data` to this node, resolving to `true` if it acknowledged.
+    fn write(&self, data: &Data) -> impl Future<Output = bool>;
+}
+
+/// Returned when too few nodes acknowledged a write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum QuorumError {
+    NotReached { acks: usize, required: usize },
+}
+
+impl fmt::Display for QuorumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuorumError::NotReached { acks, required } => {
+                write!(f, "quorum not reached: {acks} of {required} acks")
+            }
+        }
+    }
+}
+
+impl std::error::Error for QuorumError {}
+
+/// Writes `data` to all `nodes` concurrently and returns once a majority acked.
+///
+/// Remaining in-flight writes are dropped as soon as quorum is reached.
+pub async fn quorum_write<N: Node>(nodes: Vec<N>, data: Data) -> Result<(), QuorumError> {
+    if nodes.is_empty() {
+        return Ok(());
+    }
+
+    let required = nodes.len() / 2 + 1;
+    let mut pending: FuturesUnordered<_> = nodes.iter().map(|node| node.write(&data)).collect();
+
+    let mut acks = 0;
+    while let Some(acked) = pending.next().await {
+        if acked {
+            acks += 1;
+            if acks >= required {
+                return Ok(());
+            }
+        }
+    }
+
+    Err(QuorumError::NotReached { acks, required })
+}
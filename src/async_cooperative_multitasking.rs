@@ -0,0 +1,63 @@
+//! Explicit cooperative yield points with a configurable granularity.
+//!
+//! Yielding on every item is wasteful for cheap bodies and yielding never can
+//! starve other tasks. `CoopScheduler` counts down a budget and only yields
+//! to the executor when it runs out.
+
+use std::cell::Cell;
+use std::future::Future;
+
+pub struct CoopScheduler {
+    budget: Cell<u32>,
+    initial_budget: u32,
+}
+
+impl CoopScheduler {
+    /// Yields once every `budget` calls to `yield_if_needed`.
+    ///
+    /// `u32::MAX` is treated as an unlimited budget that never yields.
+    pub fn new(budget: u32) -> Self {
+        let budget = budget.max(1);
+        Self {
+            budget: Cell::new(budget),
+            initial_budget: budget,
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(u32::MAX)
+    }
+
+    /// Spends one unit of budget, yielding to the executor if it ran out.
+    pub async fn yield_if_needed(&self) {
+        if self.initial_budget == u32::MAX {
+            return;
+        }
+        let remaining = self.budget.get() - 1;
+        if remaining == 0 {
+            self.budget.set(self.initial_budget);
+            tokio::task::yield_now().await;
+        } else {
+            self.budget.set(remaining);
+        }
+    }
+}
+
+/// Processes `data` with a yield point every `budget` items.
+///
+/// Empty input returns before the scheduler is created.
+pub async fn run_cooperatively<T, F, Fut>(data: Vec<T>, budget: u32, process: F)
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if data.is_empty() {
+        return;
+    }
+
+    let scheduler = CoopScheduler::new(budget);
+    for item in data {
+        scheduler.yield_if_needed().await;
+        process(item).await;
+    }
+}
@@ -0,0 +1,80 @@
+//! Ownership-passing vs reference loops and the state machines they produce.
+//!
+//! `for item in data` moves the `Vec` into a `vec::IntoIter`, `for item in
+//! &data` borrows it through a `slice::Iter`, and `for item in &mut data`
+//! through a `slice::IterMut`. The iterator is held across the `.await`, so
+//! its type decides the size of the suspended state.
+//!
+//! MIR (empty data; dump with `-Z unpretty=mir` on nightly):
+//!
+//! owned (`Vec<i32>`), iterator field `vec::IntoIter<i32>`, future 72 bytes:
+//!   bb0 -> bb1 (into_iter) -> bb3 (next) -> bb4 (match None)
+//!       -> bb7 (drop IntoIter: frees the buffer) -> bb15 (return Ready)
+//!
+//! borrowed (`&Vec<i32>`), iterator field `slice::Iter<'_, i32>`, future 40 bytes:
+//!   bb0 -> bb1 (into_iter) -> bb3 (next) -> bb4 (match None) -> bb7 (return Ready)
+//!
+//! mutably borrowed (`&mut Vec<i32>`), iterator field `slice::IterMut<'_, i32>`,
+//! future 40 bytes:
+//!   bb0 -> bb1 (into_iter) -> bb3 (next) -> bb4 (match None) -> bb7 (return Ready)
+//!
+//! The owned loop is the only one with drop glue on its exit path, and its
+//! future also stores the `Vec` itself before the loop starts. With the
+//! empty-check in front, all three collapse to `is_empty` + branch + return
+//! (the owned variant still drops the `Vec`, which is a no-op when it never
+//! allocated).
+//!
+//! Sizes are for x86_64 with `step` as the only awaitee.
+
+#![allow(clippy::ptr_arg)] // `&Vec<i32>` is compared against `Vec<i32>` on purpose
+
+use std::hint::black_box;
+
+/// The per-item async step shared by every loop below.
+pub async fn step(item: i32) {
+    black_box(item);
+}
+
+pub async fn owned_with_check(data: Vec<i32>) {
+    if !data.is_empty() {
+        for item in data {
+            step(item).await;
+        }
+    }
+}
+
+pub async fn owned_no_check(data: Vec<i32>) {
+    for item in data {
+        step(item).await;
+    }
+}
+
+pub async fn borrowed_with_check(data: &Vec<i32>) {
+    if !data.is_empty() {
+        for item in data {
+            step(*item).await;
+        }
+    }
+}
+
+pub async fn borrowed_no_check(data: &Vec<i32>) {
+    for item in data {
+        step(*item).await;
+    }
+}
+
+pub async fn mut_borrowed_with_check(data: &mut Vec<i32>) {
+    if !data.is_empty() {
+        for item in data {
+            *item += 1;
+            step(*item).await;
+        }
+    }
+}
+
+pub async fn mut_borrowed_no_check(data: &mut Vec<i32>) {
+    for item in data {
+        *item += 1;
+        step(*item).await;
+    }
+}
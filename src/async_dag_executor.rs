@@ -0,0 +1,63 @@
+//! Level-by-level async execution of a task DAG.
+//!
+//! Tasks are placed on levels; every task on a level may depend on anything
+//! on an earlier level, and tasks on the same level run concurrently. Stage
+//! schedulers often leave levels with nothing ready, and the guard skips
+//! those without building a `join_all` for them.
+
+use std::future::Future;
+
+use futures::future::join_all;
+
+/// A unit of work in the DAG.
+pub trait Task {
+    type Output;
+
+    fn run(&self) -> impl Future<Output = Self::Output>;
+}
+
+pub struct Dag<T> {
+    levels: Vec<Vec<T>>,
+}
+
+impl<T: Task> Dag<T> {
+    pub fn new() -> Self {
+        Self { levels: Vec::new() }
+    }
+
+    /// Places `task` on `level`, adding empty levels up to it if needed.
+    pub fn add_task(&mut self, level: usize, task: T) {
+        if self.levels.len() <= level {
+            self.levels.resize_with(level + 1, Vec::new);
+        }
+        self.levels[level].push(task);
+    }
+
+    /// The levels in execution order, e.g. to run them without the guard.
+    pub fn levels(&self) -> &[Vec<T>] {
+        &self.levels
+    }
+
+    /// Number of levels with no tasks, i.e. the `join_all`s the guard saves.
+    pub fn empty_levels(&self) -> usize {
+        self.levels.iter().filter(|level| level.is_empty()).count()
+    }
+}
+
+impl<T: Task> Default for Dag<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Runs `dag` level by level, returning outputs in level order.
+pub async fn execute_dag<T: Task>(dag: &Dag<T>) -> Vec<T::Output> {
+    let mut outputs = Vec::new();
+    for level in &dag.levels {
+        if level.is_empty() {
+            continue;
+        }
+        outputs.extend(join_all(level.iter().map(Task::run)).await);
+    }
+    outputs
+}
@@ -0,0 +1,160 @@
+//! Debounced batch processing: wait for a quiet period, then handle
+//! everything that arrived as one batch.
+//!
+//! A burst of events (file-watcher notifications, keystrokes) is collected
+//! until no new event has arrived for `quiet_period`, and the processor runs
+//! once for the whole burst. A batch only starts on a received event, so it
+//! is never empty in practice; the guard before processing is kept anyway so
+//! a processor is never called with nothing to do.
+
+use std::future::Future;
+use std::mem;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+/// Marker for types that can be debounced.
+pub trait Event: Send {}
+
+/// Handles one debounced batch.
+pub trait AsyncBatchProcessor<E> {
+    fn process_batch(&mut self, batch: Vec<E>) -> impl Future<Output = ()>;
+}
+
+/// Collects events from `rx` into batches separated by `quiet_period` of
+/// silence and passes each batch to `processor`. Returns once every sender
+/// is gone, after processing the batch that was still open.
+pub async fn debounce_batch<E: Event>(
+    mut rx: mpsc::Receiver<E>,
+    quiet_period: Duration,
+    mut processor: impl AsyncBatchProcessor<E>,
+) {
+    let mut batch = Vec::new();
+    while let Some(first) = rx.recv().await {
+        batch.push(first);
+        let closed = loop {
+            match tokio::time::timeout(quiet_period, rx.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                Ok(None) => break true,
+                Err(_quiet) => break false,
+            }
+        };
+
+        if !batch.is_empty() {
+            processor.process_batch(mem::take(&mut batch)).await;
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::time::sleep;
+
+    impl Event for u32 {}
+
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<Vec<u32>>>>);
+
+    impl AsyncBatchProcessor<u32> for Recorder {
+        async fn process_batch(&mut self, batch: Vec<u32>) {
+            self.0.lock().unwrap().push(batch);
+        }
+    }
+
+    impl Recorder {
+        fn batches(&self) -> Vec<Vec<u32>> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    const QUIET: Duration = Duration::from_millis(10);
+
+    /// Runs the debouncer while `send` drives the channel, then waits for it
+    /// to finish.
+    async fn run<F, Fut>(send: F) -> Vec<Vec<u32>>
+    where
+        F: FnOnce(mpsc::Sender<u32>) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let (tx, rx) = mpsc::channel(16);
+        let recorder = Recorder::default();
+        let debouncer = tokio::spawn(debounce_batch(rx, QUIET, recorder.clone()));
+        send(tx).await;
+        debouncer.await.unwrap();
+        recorder.batches()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_inside_quiet_period_is_one_batch() {
+        let batches = run(|tx| async move {
+            for event in 0..3 {
+                tx.send(event).await.unwrap();
+                sleep(QUIET / 2).await;
+            }
+        })
+        .await;
+        assert_eq!(batches, [vec![0, 1, 2]]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn event_after_quiet_period_starts_a_new_batch() {
+        let batches = run(|tx| async move {
+            tx.send(0).await.unwrap();
+            sleep(QUIET * 2).await;
+            tx.send(1).await.unwrap();
+        })
+        .await;
+        assert_eq!(batches, [vec![0], vec![1]]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn each_event_restarts_the_quiet_period() {
+        // Total span is well over one quiet period, but no gap reaches it.
+        let batches = run(|tx| async move {
+            for event in 0..5 {
+                tx.send(event).await.unwrap();
+                sleep(QUIET - Duration::from_millis(1)).await;
+            }
+        })
+        .await;
+        assert_eq!(batches, [vec![0, 1, 2, 3, 4]]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn close_during_open_batch_flushes_it() {
+        // The senders go away before the quiet period ends; the pending batch
+        // must still be processed instead of being lost with the timer.
+        let batches = run(|tx| async move {
+            tx.send(0).await.unwrap();
+            tx.send(1).await.unwrap();
+            drop(tx);
+        })
+        .await;
+        assert_eq!(batches, [vec![0, 1]]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn events_buffered_before_the_debouncer_runs_are_one_batch() {
+        // Events race ahead of the debouncer task and are all queued in the
+        // channel by the time it first polls.
+        let (tx, rx) = mpsc::channel(16);
+        for event in 0..4 {
+            tx.send(event).await.unwrap();
+        }
+        drop(tx);
+        let recorder = Recorder::default();
+        debounce_batch(rx, QUIET, recorder.clone()).await;
+        assert_eq!(recorder.batches(), [vec![0, 1, 2, 3]]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn no_events_means_no_batches() {
+        let batches = run(|tx| async move { drop(tx) }).await;
+        assert!(batches.is_empty());
+    }
+}
@@ -0,0 +1,79 @@
+//! Ordered async processing of a buffered event log.
+//!
+//! Event-sourced state is rebuilt by applying events in the order they were
+//! recorded. `drain(..)` hands out owned events and empties the buffer;
+//! `iter()` lends them and leaves the buffer in place. Both visit elements
+//! front to back, and the empty-check only decides whether the loop starts.
+
+use std::future::Future;
+
+/// Applies every event to `apply` in order, leaving `events` empty.
+pub async fn drain_in_order<const CHECK: bool, T, F, Fut>(events: &mut Vec<T>, mut apply: F)
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if CHECK && events.is_empty() {
+        return;
+    }
+    for event in events.drain(..) {
+        apply(event).await;
+    }
+}
+
+/// Applies every event to `apply` in order, leaving `events` untouched.
+pub async fn iter_in_order<const CHECK: bool, T, F, Fut>(events: &[T], mut apply: F)
+where
+    F: FnMut(&T) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    if CHECK && events.is_empty() {
+        return;
+    }
+    for event in events {
+        apply(event).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn events() -> Vec<i32> {
+        vec![3, 1, 4, 1, 5, 9, 2, 6]
+    }
+
+    fn drained<const CHECK: bool>(mut events: Vec<i32>) -> Vec<i32> {
+        let mut seen = Vec::new();
+        block_on(drain_in_order::<CHECK, _, _, _>(&mut events, |e| {
+            seen.push(e);
+            tokio::task::yield_now()
+        }));
+        assert!(events.is_empty());
+        seen
+    }
+
+    fn iterated<const CHECK: bool>(events: &[i32]) -> Vec<i32> {
+        let mut seen = Vec::new();
+        block_on(iter_in_order::<CHECK, _, _, _>(events, |e| {
+            seen.push(*e);
+            tokio::task::yield_now()
+        }));
+        seen
+    }
+
+    #[test]
+    fn drain_and_iter_apply_events_in_the_same_order() {
+        assert_eq!(drained::<true>(events()), events());
+        assert_eq!(iterated::<true>(&events()), events());
+    }
+
+    #[test]
+    fn empty_check_does_not_change_order() {
+        assert_eq!(drained::<true>(events()), drained::<false>(events()));
+        assert_eq!(iterated::<true>(&events()), iterated::<false>(&events()));
+        assert!(drained::<true>(vec![]).is_empty());
+        assert!(iterated::<true>(&[]).is_empty());
+    }
+}
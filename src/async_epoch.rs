@@ -0,0 +1,54 @@
+//! Epoch-based reclamation for garbage retired by lock-free structures.
+//!
+//! Nodes unlinked from a lock-free structure may still be read by tasks that
+//! loaded a pointer to them earlier, so they are retired into a garbage list
+//! and freed only once the global epoch has moved past every such reader.
+//! Most reclamation passes find the list empty; those return without
+//! touching the shared epoch counter, so they cause no cache-line traffic.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[derive(Debug, Default)]
+pub struct EpochGuard {
+    epoch: AtomicU64,
+}
+
+impl EpochGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current global epoch.
+    pub fn current(&self) -> u64 {
+        self.epoch.load(Ordering::Acquire)
+    }
+
+    /// Moves to the next epoch, returning the previous one.
+    pub fn advance(&self) -> u64 {
+        self.epoch.fetch_add(1, Ordering::AcqRel)
+    }
+}
+
+/// Passes every item of `garbage` to `reclaimer`, then advances the epoch so
+/// later passes know this batch is gone. Returns the number of items reclaimed.
+pub async fn reclaim_in_epoch<C, F, Fut>(guard: &EpochGuard, garbage: C, mut reclaimer: F) -> usize
+where
+    C: IntoIterator,
+    C::IntoIter: ExactSizeIterator,
+    F: FnMut(u64, C::Item) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let garbage = garbage.into_iter();
+    let count = garbage.len();
+    if count == 0 {
+        return 0;
+    }
+
+    let epoch = guard.current();
+    for item in garbage {
+        reclaimer(epoch, item).await;
+    }
+    guard.advance();
+    count
+}
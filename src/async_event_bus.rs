@@ -0,0 +1,89 @@
+//! A type-keyed async event bus whose subscribers are awaited in order.
+//!
+//! Publishing an event type nobody subscribed to is the common case for a
+//! general-purpose bus. A lock-free bitmap of subscribed `TypeId`s is checked
+//! first, so those publishes return without acquiring the `RwLock` read guard;
+//! types that pass the filter still get the usual empty-check under the lock.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::future::BoxFuture;
+use tokio::sync::RwLock;
+
+/// Marker for types that can be published on the bus.
+pub trait Event: Any + Send + Sync {}
+
+/// A subscriber, invoked with the bus-wide event type `E`.
+pub trait AsyncSubscriber<E>: Send + Sync {
+    fn on_event<'a>(&'a self, event: &'a E) -> BoxFuture<'a, ()>;
+}
+
+const FILTER_WORDS: usize = 4;
+
+type SubscriberMap<E> = HashMap<TypeId, Vec<Box<dyn AsyncSubscriber<E>>>>;
+
+pub struct AsyncEventBus<E: Event> {
+    subscribers: RwLock<SubscriberMap<E>>,
+    /// Bloom-style filter over subscribed `TypeId`s: a clear bit means the
+    /// type definitely has no subscribers. Saturates as more types subscribe.
+    subscribed: [AtomicU64; FILTER_WORDS],
+}
+
+/// Word index and bit mask for `id` in the subscription filter.
+fn filter_slot(id: TypeId) -> (usize, u64) {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let bit = hasher.finish() % (FILTER_WORDS as u64 * 64);
+    ((bit / 64) as usize, 1 << (bit % 64))
+}
+
+impl<E: Event> AsyncEventBus<E> {
+    pub fn new() -> Self {
+        Self {
+            subscribers: RwLock::new(HashMap::new()),
+            subscribed: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// Registers `subscriber` for events published as type `T`.
+    pub fn subscribe<T: Event>(&mut self, subscriber: Box<dyn AsyncSubscriber<E>>) {
+        let id = TypeId::of::<T>();
+        let (word, mask) = filter_slot(id);
+        self.subscribed[word].fetch_or(mask, Ordering::Relaxed);
+        self.subscribers
+            .get_mut()
+            .entry(id)
+            .or_default()
+            .push(subscriber);
+    }
+
+    /// Delivers `event` to every subscriber of `T`, in subscription order.
+    pub async fn publish<T: Event + Into<E>>(&self, event: T) {
+        let id = TypeId::of::<T>();
+        let (word, mask) = filter_slot(id);
+        if self.subscribed[word].load(Ordering::Relaxed) & mask == 0 {
+            return;
+        }
+
+        let subscribers = self.subscribers.read().await;
+        let subscribers = match subscribers.get(&id) {
+            Some(subscribers) if !subscribers.is_empty() => subscribers,
+            _ => return,
+        };
+
+        let event = event.into();
+        for subscriber in subscribers {
+            subscriber.on_event(&event).await;
+        }
+    }
+}
+
+impl<E: Event> Default for AsyncEventBus<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
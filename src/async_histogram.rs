@@ -0,0 +1,26 @@
+//! Recording batches of samples into an HDR histogram from async code.
+//!
+//! Each `record` is CPU work (log-scale bucketing plus a counter update),
+//! so a large batch could hold the worker thread for a long time. The loop
+//! charges tokio's cooperative budget per sample and yields when it runs out,
+//! which makes it a mixed CPU + async loop. Metric flushes frequently carry
+//! no samples; those return before touching the histogram or the budget.
+
+use hdrhistogram::Histogram;
+
+/// Records every value into `histogram`, clamping values outside its range.
+pub async fn update_histogram<C>(values: C, histogram: &mut Histogram<u64>)
+where
+    C: IntoIterator<Item = u64>,
+    C::IntoIter: ExactSizeIterator,
+{
+    let values = values.into_iter();
+    if values.len() == 0 {
+        return;
+    }
+
+    for value in values {
+        histogram.saturating_record(value);
+        tokio::task::coop::consume_budget().await;
+    }
+}
@@ -0,0 +1,69 @@
+//! Runtime profiling to decide which async loops are worth guarding.
+//!
+//! Call sites report each time their loop runs and whether its input was
+//! empty. A loop that mostly runs with nothing to do pays the empty-iteration
+//! overhead on most calls and should get the empty-check; one that almost
+//! always has work gains nothing from it.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Default)]
+pub struct HotPathDetector {
+    call_counts: HashMap<&'static str, u64>,
+    empty_counts: HashMap<&'static str, u64>,
+}
+
+impl HotPathDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one run of the loop at `name`.
+    pub fn record_call(&mut self, name: &'static str, was_empty: bool) {
+        *self.call_counts.entry(name).or_default() += 1;
+        if was_empty {
+            *self.empty_counts.entry(name).or_default() += 1;
+        }
+    }
+
+    /// True once more than half of the recorded calls to `name` were empty.
+    /// Loops that were never recorded are not guarded.
+    pub fn should_guard(&self, name: &'static str) -> bool {
+        let calls = self.call_counts.get(name).copied().unwrap_or(0);
+        let empty = self.empty_counts.get(name).copied().unwrap_or(0);
+        empty * 2 > calls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector(name: &'static str, empty: u64, non_empty: u64) -> HotPathDetector {
+        let mut detector = HotPathDetector::new();
+        for _ in 0..empty {
+            detector.record_call(name, true);
+        }
+        for _ in 0..non_empty {
+            detector.record_call(name, false);
+        }
+        detector
+    }
+
+    #[test]
+    fn unrecorded_loop_is_not_guarded() {
+        let detector = detector("recorded", 5, 0);
+        assert!(!detector.should_guard("never recorded"));
+    }
+
+    #[test]
+    fn exactly_half_empty_is_not_guarded() {
+        assert!(!detector("drain", 5, 5).should_guard("drain"));
+    }
+
+    #[test]
+    fn mostly_empty_is_guarded() {
+        assert!(detector("drain", 6, 5).should_guard("drain"));
+        assert!(detector("drain", 1, 0).should_guard("drain"));
+    }
+}
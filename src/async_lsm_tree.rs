@@ -0,0 +1,46 @@
+//! Background compaction for an LSM tree's sorted string tables.
+//!
+//! Writes land in SSTables that may hold several versions of a key; the
+//! compaction loop rewrites each table sorted and with only the newest
+//! version left. Compaction is triggered far less often than it is checked
+//! for, so most calls arrive with no tables and return at the guard.
+
+/// An immutable table of `(key, value)` writes, oldest first.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SsTable {
+    pub entries: Vec<(u64, u64)>,
+}
+
+impl SsTable {
+    pub fn new(entries: Vec<(u64, u64)>) -> Self {
+        Self { entries }
+    }
+}
+
+/// Sorts `table` by key and keeps only the last write for each key.
+pub async fn compact_table(mut table: SsTable) -> SsTable {
+    // Stable, so equal keys stay oldest first and the last one wins below.
+    table.entries.sort_by_key(|&(key, _)| key);
+    let mut compacted: Vec<(u64, u64)> = Vec::with_capacity(table.entries.len());
+    for (key, value) in table.entries {
+        match compacted.last_mut() {
+            Some(last) if last.0 == key => last.1 = value,
+            _ => compacted.push((key, value)),
+        }
+    }
+    // Compaction runs in the background; let foreground tasks in between tables.
+    tokio::task::yield_now().await;
+    SsTable::new(compacted)
+}
+
+/// Compacts every table in `tables`, in order.
+pub async fn maybe_compact(tables: Vec<SsTable>) -> Vec<SsTable> {
+    if tables.is_empty() {
+        return tables;
+    }
+    let mut compacted = Vec::with_capacity(tables.len());
+    for table in tables {
+        compacted.push(compact_table(table).await);
+    }
+    compacted
+}
@@ -0,0 +1,82 @@
+//! Read-ahead buffering for sequential async loads.
+//!
+//! The buffer keeps up to `prefetch_count` loads running as spawned tasks
+//! and moves them into `ready` as they finish, oldest first, so items come
+//! out in the order they were requested. A batch only waits on in-flight
+//! loads when nothing is ready; with enough read-ahead that check usually
+//! passes and the caller never blocks.
+
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+
+pub struct PrefetchBuffer<T> {
+    ready: Vec<T>,
+    in_flight: Vec<JoinHandle<T>>,
+    prefetch_count: usize,
+}
+
+impl<T: Send + 'static> PrefetchBuffer<T> {
+    /// # Panics
+    ///
+    /// If `prefetch_count` is zero.
+    pub fn new(prefetch_count: usize) -> Self {
+        assert!(prefetch_count > 0, "prefetch_count must be non-zero");
+        Self {
+            ready: Vec::new(),
+            in_flight: Vec::with_capacity(prefetch_count),
+            prefetch_count,
+        }
+    }
+
+    /// Loads currently running in the background.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.len()
+    }
+
+    /// Returns every loaded item in request order, waiting for the in-flight
+    /// loads only if none has finished yet, and tops the read-ahead back up
+    /// with `loader`.
+    ///
+    /// Must be called within a Tokio runtime.
+    ///
+    /// # Panics
+    ///
+    /// If a load panicked.
+    pub async fn next_batch<F, Fut>(&mut self, mut loader: F) -> Vec<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        self.fill(&mut loader);
+
+        let finished = self
+            .in_flight
+            .iter()
+            .take_while(|h| h.is_finished())
+            .count();
+        for handle in self.in_flight.drain(..finished) {
+            self.ready
+                .push(handle.await.expect("prefetch load panicked"));
+        }
+        if self.ready.is_empty() {
+            for handle in self.in_flight.drain(..) {
+                self.ready
+                    .push(handle.await.expect("prefetch load panicked"));
+            }
+        }
+
+        self.fill(&mut loader);
+        std::mem::take(&mut self.ready)
+    }
+
+    fn fill<F, Fut>(&mut self, loader: &mut F)
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        while self.in_flight.len() < self.prefetch_count {
+            self.in_flight.push(tokio::spawn(loader()));
+        }
+    }
+}
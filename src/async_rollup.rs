@@ -0,0 +1,63 @@
+//! Time-window rollups for telemetry events.
+//!
+//! Raw events are sorted by timestamp, grouped into fixed windows and each
+//! window is reduced to count/sum/min/max. Collection ticks that saw no
+//! events return before allocating or sorting anything.
+
+use std::time::Duration;
+
+/// A sample with a timestamp, measured from any fixed origin.
+pub trait TimeseriesEvent {
+    fn timestamp(&self) -> Duration;
+    fn value(&self) -> f64;
+}
+
+/// Aggregate of every event whose timestamp falls in
+/// `window_start..window_start + window`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RolledUpEvent {
+    pub window_start: Duration,
+    pub count: u64,
+    pub sum: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Rolls `events` up into windows of length `window`, in time order. Windows
+/// without events are omitted. Panics if `window` is zero.
+pub async fn rollup_window<E: TimeseriesEvent>(
+    mut events: Vec<E>,
+    window: Duration,
+) -> Vec<RolledUpEvent> {
+    if events.is_empty() {
+        return Vec::new();
+    }
+    assert!(!window.is_zero(), "rollup window must be non-zero");
+
+    let window_ns = window.as_nanos();
+    let window_of = |e: &E| e.timestamp().as_nanos() / window_ns;
+    events.sort_unstable_by_key(|e| e.timestamp());
+
+    let mut rollups = Vec::new();
+    for group in events.chunk_by(|a, b| window_of(a) == window_of(b)) {
+        let start = window_of(&group[0]) * window_ns;
+        let mut rollup = RolledUpEvent {
+            window_start: Duration::from_nanos(start as u64),
+            count: 0,
+            sum: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        };
+        for event in group {
+            let value = event.value();
+            rollup.count += 1;
+            rollup.sum += value;
+            rollup.min = rollup.min.min(value);
+            rollup.max = rollup.max.max(value);
+        }
+        rollups.push(rollup);
+        // Aggregation is CPU work; give the scheduler a chance between windows.
+        tokio::task::coop::consume_budget().await;
+    }
+    rollups
+}
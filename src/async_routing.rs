@@ -0,0 +1,64 @@
+//! An async message router that runs every handler registered for a route.
+//!
+//! Most traffic in a router hits a small set of routes; messages for routes
+//! with no handlers (including unknown routes) return before any handler
+//! future is created.
+
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+
+/// A message that knows which route it belongs to.
+pub trait Message {
+    fn route_key(&self) -> &str;
+}
+
+/// A handler invoked for every message on its route.
+pub trait AsyncHandler<M>: Send + Sync {
+    fn handle<'a>(&'a self, msg: &'a M) -> BoxFuture<'a, ()>;
+}
+
+pub struct AsyncRouter<M: Message> {
+    routes: HashMap<String, Vec<Box<dyn AsyncHandler<M>>>>,
+}
+
+impl<M: Message> AsyncRouter<M> {
+    pub fn new() -> Self {
+        Self {
+            routes: HashMap::new(),
+        }
+    }
+
+    /// Declares `route` without attaching any handlers to it.
+    pub fn add_route(&mut self, route: impl Into<String>) {
+        self.routes.entry(route.into()).or_default();
+    }
+
+    /// Appends `handler` to `route`, creating the route if needed.
+    pub fn register(&mut self, route: impl Into<String>, handler: Box<dyn AsyncHandler<M>>) {
+        self.routes.entry(route.into()).or_default().push(handler);
+    }
+
+    /// The handlers registered for `route`, or `None` for an unknown route.
+    pub fn handlers(&self, route: &str) -> Option<&[Box<dyn AsyncHandler<M>>]> {
+        self.routes.get(route).map(Vec::as_slice)
+    }
+
+    /// Runs each handler registered for `msg`'s route, in registration order.
+    pub async fn route(&self, msg: M) {
+        let handlers = match self.routes.get(msg.route_key()) {
+            Some(handlers) if !handlers.is_empty() => handlers,
+            _ => return,
+        };
+
+        for handler in handlers {
+            handler.handle(&msg).await;
+        }
+    }
+}
+
+impl<M: Message> Default for AsyncRouter<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
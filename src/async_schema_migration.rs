@@ -0,0 +1,85 @@
+//! An async runner for pending database schema migrations.
+//!
+//! On startup a service applies every migration newer than the database's
+//! schema version. Almost every start finds none, and the guard returns
+//! before the rollback log is set up. When one migration fails, the ones
+//! already applied by this run are reverted newest first.
+
+use std::fmt;
+use std::future::Future;
+
+/// One schema change and the statement that reverts it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Migration {
+    pub version: u64,
+    pub up: String,
+    pub down: String,
+}
+
+impl Migration {
+    pub fn new(version: u64, up: impl Into<String>, down: impl Into<String>) -> Self {
+        Self {
+            version,
+            up: up.into(),
+            down: down.into(),
+        }
+    }
+}
+
+/// A database connection that can run schema statements.
+pub trait AsyncDb {
+    fn execute(&self, statement: &str) -> impl Future<Output = Result<(), String>>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// Migration `version` failed; every migration before it was rolled back.
+    Failed { version: u64, reason: String },
+    /// Rolling back `version` failed after a later migration had failed.
+    RollbackFailed { version: u64, reason: String },
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::Failed { version, reason } => {
+                write!(f, "migration {version} failed: {reason}")
+            }
+            MigrationError::RollbackFailed { version, reason } => {
+                write!(f, "rollback of migration {version} failed: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// Applies `pending` in order, rolling back this run's migrations on failure.
+pub async fn run_pending_migrations<D: AsyncDb>(
+    pending: Vec<Migration>,
+    db: &D,
+) -> Result<(), MigrationError> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let mut applied: Vec<&Migration> = Vec::with_capacity(pending.len());
+    for migration in &pending {
+        if let Err(reason) = db.execute(&migration.up).await {
+            for done in applied.iter().rev() {
+                db.execute(&done.down)
+                    .await
+                    .map_err(|reason| MigrationError::RollbackFailed {
+                        version: done.version,
+                        reason,
+                    })?;
+            }
+            return Err(MigrationError::Failed {
+                version: migration.version,
+                reason,
+            });
+        }
+        applied.push(migration);
+    }
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! Per-shard async processing for hash-sharded data.
+//!
+//! Items are hashed into one of `N` shards; each shard is drained by its own
+//! async loop. Under skewed load most shards are empty on a given pass, and
+//! the guard turns each of those into a single length check.
+
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+
+pub struct ShardedProcessor<T, const N: usize> {
+    shards: [Vec<T>; N],
+}
+
+impl<T, const N: usize> ShardedProcessor<T, N> {
+    pub fn new() -> Self {
+        Self {
+            shards: std::array::from_fn(|_| Vec::new()),
+        }
+    }
+
+    /// Routes `item` to the shard selected by its hash.
+    pub fn insert(&mut self, item: T)
+    where
+        T: Hash,
+    {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let shard = (hasher.finish() % N as u64) as usize;
+        self.shards[shard].push(item);
+    }
+
+    /// Direct access to the shards, e.g. to place items deterministically.
+    pub fn shards_mut(&mut self) -> &mut [Vec<T>; N] {
+        &mut self.shards
+    }
+
+    /// Drains every shard in order, skipping empty shards.
+    pub async fn process_all_shards<F, Fut>(&mut self, f: F)
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        for shard in &mut self.shards {
+            if shard.is_empty() {
+                continue;
+            }
+            for item in shard.drain(..) {
+                f(item).await;
+            }
+        }
+    }
+}
+
+impl<T, const N: usize> Default for ShardedProcessor<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
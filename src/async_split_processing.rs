@@ -0,0 +1,58 @@
+//! Splitting a mixed batch between a CPU-bound and an I/O-bound handler.
+//!
+//! Each item says which lane it belongs in. Fast items are handled inline,
+//! charging tokio's cooperative budget per item so a long run still yields;
+//! slow items are awaited one by one. The two lanes run concurrently. An
+//! empty batch returns before splitting, and a lane that received no items
+//! is not started.
+
+use std::future::Future;
+
+/// Items that know which handler they need.
+pub trait Categorizable {
+    /// `true` for CPU-bound items, `false` for ones that need I/O.
+    fn is_fast(&self) -> bool;
+}
+
+/// Handles an item synchronously, without awaiting.
+pub trait FastHandler<T> {
+    fn handle(&self, item: T);
+}
+
+/// Handles an item that has to wait on I/O.
+pub trait SlowHandler<T> {
+    fn handle(&self, item: T) -> impl Future<Output = ()>;
+}
+
+/// Routes every item to `fast_handler` or `slow_handler` and runs both lanes
+/// concurrently, each in the order its items appeared in `items`.
+pub async fn split_process<T, F, S>(items: Vec<T>, fast_handler: &F, slow_handler: &S)
+where
+    T: Categorizable,
+    F: FastHandler<T>,
+    S: SlowHandler<T>,
+{
+    if items.is_empty() {
+        return;
+    }
+
+    let (fast, slow): (Vec<T>, Vec<T>) = items.into_iter().partition(T::is_fast);
+    let fast_lane = async {
+        if fast.is_empty() {
+            return;
+        }
+        for item in fast {
+            fast_handler.handle(item);
+            tokio::task::coop::consume_budget().await;
+        }
+    };
+    let slow_lane = async {
+        if slow.is_empty() {
+            return;
+        }
+        for item in slow {
+            slow_handler.handle(item).await;
+        }
+    };
+    futures::join!(fast_lane, slow_lane);
+}
@@ -0,0 +1,78 @@
+//! Delta-based state synchronisation from a local node to its replicas.
+//!
+//! Each replica reports the last version it applied, and receives only the
+//! entries written after it. In standalone mode there are no replicas, and
+//! the guard returns before any delta is computed, so its cost does not
+//! depend on how much state has changed.
+
+use std::future::Future;
+
+/// A versioned key/value log. Entries are kept in version order.
+#[derive(Clone, Debug, Default)]
+pub struct State {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub version: u64,
+    pub key: u64,
+    pub value: i64,
+}
+
+/// Entries a replica is missing.
+pub type Delta = Vec<Entry>;
+
+impl State {
+    /// Appends a write with the next version number.
+    pub fn put(&mut self, key: u64, value: i64) {
+        let version = self.version() + 1;
+        self.entries.push(Entry {
+            version,
+            key,
+            value,
+        });
+    }
+
+    pub fn version(&self) -> u64 {
+        self.entries.last().map_or(0, |e| e.version)
+    }
+
+    /// Clones every entry newer than `version`.
+    pub fn delta_since(&self, version: u64) -> Delta {
+        let start = self.entries.partition_point(|e| e.version <= version);
+        self.entries[start..].to_vec()
+    }
+}
+
+pub trait Replica {
+    /// The last version this replica has applied.
+    fn acked_version(&self) -> u64;
+
+    /// Sends `delta`, resolving to `true` once the replica applied it.
+    fn apply(&self, delta: &Delta) -> impl Future<Output = bool>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncResult {
+    pub synced: usize,
+    pub failed: usize,
+}
+
+/// Sends each replica the delta it is missing, one replica at a time.
+pub async fn sync_state<R: Replica>(replicas: Vec<R>, local_state: &State) -> SyncResult {
+    let mut result = SyncResult::default();
+    if replicas.is_empty() {
+        return result;
+    }
+
+    for replica in &replicas {
+        let delta = local_state.delta_since(replica.acked_version());
+        if replica.apply(&delta).await {
+            result.synced += 1;
+        } else {
+            result.failed += 1;
+        }
+    }
+    result
+}
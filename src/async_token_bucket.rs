@@ -0,0 +1,156 @@
+//! A rate limiter that spends locally leased tokens and refills them from a
+//! shared remote source.
+//!
+//! Each node holds a local balance of tokens leased from a central source,
+//! such as a Redis counter. A batch is paid from the balance with one
+//! atomic update. Only when the balance is short does the bucket go to the
+//! remote source, and then it leases at least `lease` tokens so the
+//! following batches are local again. An empty batch costs nothing and
+//! returns before the balance is read.
+
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use futures::future::BoxFuture;
+
+/// The shared pool that local buckets lease tokens from.
+pub trait AsyncTokenSource: Send + Sync {
+    /// Leases up to `tokens`, resolving to how many were granted.
+    fn acquire(&self, tokens: u64) -> BoxFuture<'_, Result<u64, String>>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RateLimitError {
+    /// The remote source could not grant enough tokens for the batch.
+    Exhausted { requested: usize, available: i64 },
+    /// The remote source failed.
+    Remote(String),
+}
+
+impl fmt::Display for RateLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::Exhausted {
+                requested,
+                available,
+            } => {
+                write!(
+                    f,
+                    "rate limited: {requested} tokens requested, {available} available"
+                )
+            }
+            RateLimitError::Remote(reason) => write!(f, "token source failed: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for RateLimitError {}
+
+pub struct DistributedTokenBucket {
+    local: AtomicI64,
+    remote: Arc<dyn AsyncTokenSource>,
+    lease: u64,
+}
+
+impl DistributedTokenBucket {
+    /// A bucket starting with `initial` local tokens that leases at least
+    /// `lease` tokens each time it goes to `remote`.
+    pub fn new(remote: Arc<dyn AsyncTokenSource>, initial: i64, lease: u64) -> Self {
+        Self {
+            local: AtomicI64::new(initial),
+            remote,
+            lease,
+        }
+    }
+
+    /// Local tokens not yet spent.
+    pub fn available(&self) -> i64 {
+        self.local.load(Ordering::Acquire)
+    }
+
+    /// Spends one token per item, leasing more from the remote source if the
+    /// local balance is short.
+    ///
+    /// Tokens leased for a batch that still cannot be paid stay in the local
+    /// balance for later batches.
+    pub async fn consume_batch(&self, items: usize) -> Result<(), RateLimitError> {
+        if items == 0 {
+            return Ok(());
+        }
+        self.consume(items).await
+    }
+
+    /// [`consume_batch`](Self::consume_batch) without the empty-batch guard.
+    /// Only for the benchmark comparison: unlike the other modules' unguarded
+    /// copies, it needs the private balance, so it cannot live in the bench.
+    #[doc(hidden)]
+    pub async fn consume_batch_no_check(&self, items: usize) -> Result<(), RateLimitError> {
+        self.consume(items).await
+    }
+
+    async fn consume(&self, items: usize) -> Result<(), RateLimitError> {
+        let Ok(need) = i64::try_from(items) else {
+            return Err(RateLimitError::Exhausted {
+                requested: items,
+                available: self.available(),
+            });
+        };
+        if self.try_take(need) {
+            return Ok(());
+        }
+
+        let shortfall = (need - self.available()).max(0) as u64;
+        let granted = self
+            .remote
+            .acquire(shortfall.max(self.lease))
+            .await
+            .map_err(RateLimitError::Remote)?;
+        self.local.fetch_add(granted as i64, Ordering::AcqRel);
+
+        if self.try_take(need) {
+            Ok(())
+        } else {
+            Err(RateLimitError::Exhausted {
+                requested: items,
+                available: self.available(),
+            })
+        }
+    }
+
+    /// Takes `need` tokens from the local balance if it has that many.
+    fn try_take(&self, need: i64) -> bool {
+        self.local
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current >= need).then_some(current - need)
+            })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    struct NoTokens;
+
+    impl AsyncTokenSource for NoTokens {
+        fn acquire(&self, _tokens: u64) -> BoxFuture<'_, Result<u64, String>> {
+            Box::pin(async { Ok(0) })
+        }
+    }
+
+    #[test]
+    fn oversized_batch_is_rejected_without_touching_the_balance() {
+        let bucket = DistributedTokenBucket::new(Arc::new(NoTokens), 10, 100);
+        assert_eq!(
+            block_on(bucket.consume_batch(usize::MAX)),
+            Err(RateLimitError::Exhausted {
+                requested: usize::MAX,
+                available: 10,
+            })
+        );
+        assert_eq!(bucket.available(), 10);
+    }
+}
@@ -0,0 +1,70 @@
+//! An async two-phase commit coordinator.
+//!
+//! Phase one asks every participant to prepare the transaction; if all vote
+//! yes, phase two tells each of them to commit. A transaction that touches
+//! no remote participants, the common case for local transactions, has
+//! nothing to coordinate, and the guard returns before either phase starts.
+//! When a participant votes no, the ones that already prepared are told to
+//! abort.
+
+use std::fmt;
+use std::future::Future;
+
+/// One resource manager taking part in a distributed transaction.
+pub trait Participant {
+    /// Votes on `tx`: `Ok` is a yes vote, `Err` a no vote with its reason.
+    fn prepare(&self, tx: u64) -> impl Future<Output = Result<(), String>>;
+    fn commit(&self, tx: u64) -> impl Future<Output = Result<(), String>>;
+    fn abort(&self, tx: u64) -> impl Future<Output = ()>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TwoPhaseError {
+    /// Participant `index` voted no; every participant before it was aborted.
+    Aborted { index: usize, reason: String },
+    /// Participant `index` failed to commit after all of them voted yes.
+    CommitFailed { index: usize, reason: String },
+}
+
+impl fmt::Display for TwoPhaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TwoPhaseError::Aborted { index, reason } => {
+                write!(f, "participant {index} voted to abort: {reason}")
+            }
+            TwoPhaseError::CommitFailed { index, reason } => {
+                write!(f, "participant {index} failed to commit: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TwoPhaseError {}
+
+/// Runs both phases of two-phase commit for `tx` over `participants`.
+pub async fn two_phase_commit<P: Participant>(
+    tx: u64,
+    participants: &[P],
+) -> Result<(), TwoPhaseError> {
+    // One guard covers both phases: the commit phase has the same
+    // participants as the prepare phase.
+    if participants.is_empty() {
+        return Ok(());
+    }
+
+    for (index, participant) in participants.iter().enumerate() {
+        if let Err(reason) = participant.prepare(tx).await {
+            for prepared in &participants[..index] {
+                prepared.abort(tx).await;
+            }
+            return Err(TwoPhaseError::Aborted { index, reason });
+        }
+    }
+    for (index, participant) in participants.iter().enumerate() {
+        participant
+            .commit(tx)
+            .await
+            .map_err(|reason| TwoPhaseError::CommitFailed { index, reason })?;
+    }
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! Type-state wrappers that record whether a loop's input has been checked.
+//!
+//! `Guarded<C>` can only run its loop behind the empty-check, so code that
+//! takes a `Guarded` gets the fast path without remembering to write it.
+//! `Unguarded<C>` is for call sites that know their input is non-empty and
+//! want to skip the check; that promise is verified only by a
+//! `debug_assert!`, so release builds trust the caller.
+
+use std::collections::VecDeque;
+use std::future::Future;
+
+/// Collections whose emptiness can be checked before a loop starts.
+pub trait IsEmpty {
+    fn is_empty(&self) -> bool;
+}
+
+impl<T> IsEmpty for Vec<T> {
+    fn is_empty(&self) -> bool {
+        Vec::is_empty(self)
+    }
+}
+
+impl<T> IsEmpty for &[T] {
+    fn is_empty(&self) -> bool {
+        <[T]>::is_empty(self)
+    }
+}
+
+impl<T> IsEmpty for VecDeque<T> {
+    fn is_empty(&self) -> bool {
+        VecDeque::is_empty(self)
+    }
+}
+
+/// A collection whose loop always runs behind the empty-check.
+#[derive(Debug, Clone, Default)]
+pub struct Guarded<C>(C);
+
+/// A collection whose loop runs without the empty-check.
+#[derive(Debug, Clone, Default)]
+pub struct Unguarded<C>(C);
+
+impl<C: IsEmpty> Guarded<C> {
+    pub fn new(collection: C) -> Self {
+        Self(collection)
+    }
+
+    /// Runs `f` over the collection, skipping it entirely when empty.
+    pub async fn run<'a, F, Fut>(&'a self, f: F)
+    where
+        F: FnOnce(&'a C) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        if self.0.is_empty() {
+            return;
+        }
+        f(&self.0).await;
+    }
+
+    /// Drops the guard for a call site that knows the input is non-empty.
+    pub fn unguarded(self) -> Unguarded<C> {
+        Unguarded(self.0)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C: IsEmpty> Unguarded<C> {
+    pub fn new(collection: C) -> Self {
+        Self(collection)
+    }
+
+    /// Runs `f` over the collection unconditionally.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, if the collection is empty.
+    pub async fn run<'a, F, Fut>(&'a self, f: F)
+    where
+        F: FnOnce(&'a C) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        debug_assert!(
+            !self.0.is_empty(),
+            "Unguarded::run called on an empty collection"
+        );
+        f(&self.0).await;
+    }
+
+    /// Puts the empty-check back in front of the loop.
+    pub fn guarded(self) -> Guarded<C> {
+        Guarded(self.0)
+    }
+
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::cell::Cell;
+
+    #[test]
+    fn guarded_run_skips_f_on_empty_input() {
+        let calls = Cell::new(0);
+        block_on(Guarded::new(Vec::<i32>::new()).run(|_| async { calls.set(calls.get() + 1) }));
+        assert_eq!(calls.get(), 0);
+
+        block_on(Guarded::new(vec![1]).run(|_| async { calls.set(calls.get() + 1) }));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "Unguarded::run called on an empty collection")]
+    fn unguarded_run_panics_on_empty_input_in_debug() {
+        block_on(Unguarded::new(Vec::<i32>::new()).run(|_| async {}));
+    }
+}
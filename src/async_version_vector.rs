@@ -0,0 +1,76 @@
+//! Vector clock synchronisation with a node's peers.
+//!
+//! Each node keeps one counter per node it has heard from. Syncing sends the
+//! local clock to every peer, one at a time, and merges the clock it gets
+//! back, so afterwards the local clock has seen every event its peers had.
+//! A node running alone has no one to exchange with, and the guard returns
+//! before the first send.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+
+/// Event counters keyed by node id.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VectorClock {
+    counters: BTreeMap<u64, u64>,
+}
+
+impl VectorClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one local event on `node`.
+    pub fn increment(&mut self, node: u64) {
+        *self.counters.entry(node).or_default() += 1;
+    }
+
+    /// Events seen from `node`.
+    pub fn get(&self, node: u64) -> u64 {
+        self.counters.get(&node).copied().unwrap_or(0)
+    }
+
+    /// Takes the larger counter for every node either clock has seen.
+    pub fn merge(&mut self, other: &VectorClock) {
+        for (&node, &count) in &other.counters {
+            let entry = self.counters.entry(node).or_default();
+            *entry = (*entry).max(count);
+        }
+    }
+}
+
+/// A peer that trades vector clocks.
+pub trait Node {
+    /// Sends `clock`, resolving to the peer's clock, or `None` if the peer
+    /// could not be reached.
+    fn exchange(&self, clock: &VectorClock) -> impl Future<Output = Option<VectorClock>>;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncResult {
+    pub synced: usize,
+    pub unreachable: usize,
+}
+
+/// Exchanges clocks with each peer in turn, merging every reply into
+/// `local_clock` before the next send.
+pub async fn sync_vector_clock<N: Node>(
+    peers: Vec<N>,
+    local_clock: &mut VectorClock,
+) -> SyncResult {
+    let mut result = SyncResult::default();
+    if peers.is_empty() {
+        return result;
+    }
+
+    for peer in &peers {
+        match peer.exchange(local_clock).await {
+            Some(remote) => {
+                local_clock.merge(&remote);
+                result.synced += 1;
+            }
+            None => result.unreachable += 1,
+        }
+    }
+    result
+}
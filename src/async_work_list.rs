@@ -0,0 +1,27 @@
+//! A work-list driver whose steps are async and may produce more work.
+//!
+//! Compilers and static analyses iterate to a fixed point by popping an
+//! item, analysing it and queueing whatever the analysis found. Here the
+//! empty-check is the loop condition itself: on an empty list the first
+//! `pop_front()` returns `None` and no analysis future is ever built.
+
+use std::collections::VecDeque;
+use std::future::Future;
+
+/// Analyses items front to back until `work_list` is empty, appending the
+/// items each analysis returns. Returns the number of items analysed.
+///
+/// Terminates only if the analyses eventually stop producing new items.
+pub async fn process_work_list<T, F, Fut>(work_list: &mut VecDeque<T>, mut analyze: F) -> usize
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = Vec<T>>,
+{
+    let mut processed = 0;
+    while let Some(item) = work_list.pop_front() {
+        let new_items = analyze(item).await;
+        work_list.extend(new_items);
+        processed += 1;
+    }
+    processed
+}
@@ -0,0 +1,77 @@
+//! A work-stealing deque split into an owner-only half and a shared half.
+//!
+//! Modelled on Chase-Lev: the owning worker pushes and pops its `local` end
+//! without synchronisation, while other workers hand it work through the
+//! `stolen` half, which is behind a `Mutex`. The empty-check on `local` is a
+//! plain length read; on `stolen` it costs a lock/unlock pair first.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::Mutex;
+
+pub struct WorkStealingDeque<T> {
+    local: VecDeque<T>,
+    stolen: Mutex<VecDeque<T>>,
+}
+
+impl<T> WorkStealingDeque<T> {
+    pub fn new() -> Self {
+        Self {
+            local: VecDeque::new(),
+            stolen: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Pushes onto the owner's end; only the owning worker has `&mut self`.
+    pub fn push_local(&mut self, item: T) {
+        self.local.push_back(item);
+    }
+
+    /// Hands `item` to this worker from another thread.
+    pub fn push_stolen(&self, item: T) {
+        self.stolen.lock().unwrap().push_back(item);
+    }
+
+    /// Takes the oldest shared item, as a thief would.
+    pub fn steal(&self) -> Option<T> {
+        self.stolen.lock().unwrap().pop_front()
+    }
+
+    /// Processes the local half newest-first. No lock is taken.
+    pub async fn process_local<F, Fut>(&mut self, f: F)
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        if self.local.is_empty() {
+            return;
+        }
+        while let Some(item) = self.local.pop_back() {
+            f(item).await;
+        }
+    }
+
+    /// Drains the shared half under the lock, then processes it unlocked.
+    pub async fn process_stolen<F, Fut>(&self, f: F)
+    where
+        F: Fn(T) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let batch = {
+            let mut stolen = self.stolen.lock().unwrap();
+            if stolen.is_empty() {
+                return;
+            }
+            std::mem::take(&mut *stolen)
+        };
+        for item in batch {
+            f(item).await;
+        }
+    }
+}
+
+impl<T> Default for WorkStealingDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
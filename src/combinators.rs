@@ -0,0 +1,41 @@
+//! Future combinators for chaining async loops.
+//!
+//! `Option::and_then` skips its closure on `None`; `seq_and_then` does the
+//! same across two async stages, so the second loop's future is never built
+//! when the first produced nothing.
+
+use std::future::Future;
+
+/// Awaits `first`, then runs `then` on its output if it is `Some`.
+///
+/// Equivalent to `if let Some(r) = first.await { then(r).await }`; when
+/// `first` yields `None`, `then` is never called and its future is never
+/// created.
+///
+/// ```
+/// use async_loop_perf::combinators::seq_and_then;
+/// use futures::executor::block_on;
+///
+/// let mut seen = Vec::new();
+/// block_on(seq_and_then(async { None }, |v| {
+///     seen.push(v);
+///     async {}
+/// }));
+/// assert!(seen.is_empty());
+///
+/// block_on(seq_and_then(async { Some(7) }, |v| {
+///     seen.push(v);
+///     async {}
+/// }));
+/// assert_eq!(seen, [7]);
+/// ```
+pub async fn seq_and_then<Fut1, F2, Fut2, T>(first: Fut1, then: F2)
+where
+    Fut1: Future<Output = Option<T>>,
+    F2: FnOnce(T) -> Fut2,
+    Fut2: Future<Output = ()>,
+{
+    if let Some(output) = first.await {
+        then(output).await;
+    }
+}
@@ -0,0 +1,166 @@
+//! A waker that lives on the polling thread's stack.
+//!
+//! A waker built from an `Arc` (through `ArcWake`, `std::task::Wake` or a
+//! hand-written vtable such as [`CountingWaker`]) allocates once and does an
+//! atomic increment and decrement for each clone. A [`StackWaker`] is just a
+//! struct on the caller's stack. Its `RawWaker` points at that struct,
+//! cloning copies the pointer, and waking bumps a plain counter. That is
+//! only sound on one thread, and only while every `Waker` handed out is
+//! gone before the `StackWaker` is, as spelled out on
+//! [`StackWaker::waker`].
+//!
+//! [`CountingWaker`]: crate::waker_bench::CountingWaker
+
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::task::{RawWaker, RawWakerVTable, Waker};
+
+/// A waker that counts wake-ups without allocating or touching atomics.
+#[derive(Default)]
+pub struct StackWaker {
+    wakes: UnsafeCell<usize>,
+}
+
+const STACK_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(stack_clone, stack_wake, stack_wake, stack_drop);
+
+// SAFETY (all four): `data` is a `&StackWaker` that, per the contract of
+// `StackWaker::waker`, is still alive and is only used on its own thread.
+
+unsafe fn stack_clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &STACK_VTABLE)
+}
+
+unsafe fn stack_wake(data: *const ()) {
+    let waker = unsafe { &*data.cast::<StackWaker>() };
+    unsafe { *waker.wakes.get() += 1 };
+}
+
+unsafe fn stack_drop(_: *const ()) {}
+
+/// A `Waker` borrowed from a [`StackWaker`]; dropping it does nothing.
+pub struct StackWakerRef<'a> {
+    waker: ManuallyDrop<Waker>,
+    _owner: PhantomData<&'a StackWaker>,
+}
+
+impl Deref for StackWakerRef<'_> {
+    type Target = Waker;
+
+    fn deref(&self) -> &Waker {
+        &self.waker
+    }
+}
+
+impl StackWaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A `Waker` pointing at this `StackWaker`.
+    ///
+    /// # Safety
+    ///
+    /// The counter behind the waker is a plain `usize`, and clones of the
+    /// waker carry a bare pointer to `self`. So the caller must ensure that
+    /// no clone of the returned waker outlives `self`, and that the waker
+    /// and its clones are only woken on the current thread. Polling a
+    /// future that sends its waker to another thread or stores it past the
+    /// poll breaks this; plain loops polled in place, like the benchmark's,
+    /// do not.
+    pub unsafe fn waker(&self) -> StackWakerRef<'_> {
+        let data = (self as *const StackWaker).cast::<()>();
+        // SAFETY: the vtable only reads `data` as a `&StackWaker`, which the
+        // caller keeps alive and on this thread.
+        let waker = unsafe { Waker::from_raw(RawWaker::new(data, &STACK_VTABLE)) };
+        StackWakerRef {
+            waker: ManuallyDrop::new(waker),
+            _owner: PhantomData,
+        }
+    }
+
+    /// How many times a waker from this `StackWaker` was woken.
+    pub fn wakes(&self) -> usize {
+        // SAFETY: `StackWaker` is `!Sync` and no wake runs during this read.
+        unsafe { *self.wakes.get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    use futures::task::{ArcWake, waker_ref};
+
+    #[derive(Default)]
+    struct ArcCounter(AtomicUsize);
+
+    impl ArcWake for ArcCounter {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Wakes itself `times` times, once per `Pending`, through a clone of the
+    /// waker for every other wake-up.
+    async fn self_waking(times: usize) {
+        for i in 0..times {
+            let mut pending = true;
+            std::future::poll_fn(|cx| {
+                if std::mem::take(&mut pending) {
+                    if i % 2 == 0 {
+                        cx.waker().wake_by_ref();
+                    } else {
+                        let clone = cx.waker().clone();
+                        clone.wake_by_ref();
+                    }
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            })
+            .await;
+        }
+    }
+
+    fn polls_until_ready(waker: &Waker, fut: impl Future<Output = ()>) -> usize {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(waker);
+        let mut polls = 1;
+        while fut.as_mut().poll(&mut cx).is_pending() {
+            polls += 1;
+        }
+        polls
+    }
+
+    #[test]
+    fn stack_waker_counts_wakes_like_an_arc_waker() {
+        let arc = Arc::new(ArcCounter::default());
+        let arc_polls = polls_until_ready(&waker_ref(&arc), self_waking(5));
+
+        let stack = StackWaker::new();
+        // SAFETY: `self_waking` only wakes in place, on this thread.
+        let waker = unsafe { stack.waker() };
+        let stack_polls = polls_until_ready(&waker, self_waking(5));
+
+        assert_eq!(stack_polls, arc_polls);
+        assert_eq!(stack.wakes(), arc.0.load(Ordering::Relaxed));
+        assert_eq!(stack.wakes(), 5);
+    }
+
+    #[test]
+    fn ready_future_never_wakes() {
+        let stack = StackWaker::new();
+        // SAFETY: the future never touches the waker.
+        let waker = unsafe { stack.waker() };
+        assert_eq!(polls_until_ready(&waker, async {}), 1);
+        assert_eq!(stack.wakes(), 0);
+    }
+}
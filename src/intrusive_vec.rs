@@ -0,0 +1,117 @@
+//! A fixed-capacity, stack-allocated intrusive list.
+//!
+//! Each slot stores its own `next` link inline, so building and walking the
+//! list never touches the heap. `Vec` only avoids the heap while it is empty;
+//! `IntrusiveVec` avoids it for the non-empty case too.
+//!
+//! MIR for an async loop over `list.iter()` (empty list):
+//!   no_check:   bb0 -> bb1 (IntrusiveVec::iter) -> bb2 (Iter::next, reads `head`)
+//!               -> bb3 (match None) -> return Ready
+//!   with_check: bb0 -> bb1 (IntrusiveVec::is_empty, reads `len`) -> bb2 (branch)
+//!               -> return Ready
+//!
+//! This is the same shape as the `Vec` paths in `src/mir_demo.rs`: the guard
+//! skips iterator construction and one `next()` call, regardless of where the
+//! elements live.
+
+/// A singly linked list whose nodes live inline in a `[_; N]` array.
+pub struct IntrusiveVec<T, const N: usize> {
+    links: [Link<T>; N],
+    head: Option<usize>,
+    tail: Option<usize>,
+    len: usize,
+}
+
+struct Link<T> {
+    value: Option<T>,
+    next: Option<usize>,
+}
+
+impl<T, const N: usize> IntrusiveVec<T, N> {
+    pub fn new() -> Self {
+        Self {
+            links: std::array::from_fn(|_| Link {
+                value: None,
+                next: None,
+            }),
+            head: None,
+            tail: None,
+            len: 0,
+        }
+    }
+
+    /// Appends `value`, handing it back if all `N` slots are in use.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+
+        let slot = self.len;
+        self.links[slot] = Link {
+            value: Some(value),
+            next: None,
+        };
+        match self.tail {
+            Some(tail) => self.links[tail].next = Some(slot),
+            None => self.head = Some(slot),
+        }
+        self.tail = Some(slot);
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Walks the list by following each slot's `next` link.
+    pub fn iter(&self) -> Iter<'_, T, N> {
+        Iter {
+            list: self,
+            cursor: self.head,
+            remaining: self.len,
+        }
+    }
+}
+
+impl<T, const N: usize> Default for IntrusiveVec<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Iter<'a, T, const N: usize> {
+    list: &'a IntrusiveVec<T, N>,
+    cursor: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a, T, const N: usize> Iterator for Iter<'a, T, N> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let link = &self.list.links[self.cursor?];
+        self.cursor = link.next;
+        self.remaining -= 1;
+        link.value.as_ref()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T, const N: usize> ExactSizeIterator for Iter<'_, T, N> {}
+
+impl<'a, T, const N: usize> IntoIterator for &'a IntrusiveVec<T, N> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,265 @@
+//! Shared helpers for the async loop benchmarks in `benches/async_loops.rs`.
+
+#[cfg(any(test, feature = "alloc-tracking"))]
+pub mod alloc_tracking;
+pub mod async_actor;
+pub mod async_cleanup;
+pub mod async_consensus;
+pub mod async_cooperative_multitasking;
+pub mod async_cooperative_ownership;
+pub mod async_dag_executor;
+pub mod async_debounce_batch;
+pub mod async_drain_ordering;
+pub mod async_epoch;
+pub mod async_event_bus;
+pub mod async_histogram;
+pub mod async_hot_path_detector;
+pub mod async_lsm_tree;
+pub mod async_prefetch_buffer;
+pub mod async_rollup;
+pub mod async_routing;
+pub mod async_schema_migration;
+pub mod async_segment_tree;
+pub mod async_sharded_processor;
+pub mod async_split_processing;
+pub mod async_state_sync;
+pub mod async_token_bucket;
+pub mod async_two_phase_commit;
+pub mod async_type_state;
+pub mod async_version_vector;
+pub mod async_work_list;
+pub mod async_work_stealing_deque;
+pub mod combinators;
+pub mod custom_waker;
+pub mod intrusive_vec;
+pub mod mir_demo;
+pub mod waker_bench;
+
+#[cfg(feature = "macros")]
+pub use async_loop_perf_macros::async_for_each;
+
+use std::future::{Future, poll_fn};
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+/// An iterator that decides once, at construction, whether it will yield.
+///
+/// The MIR for `no_check` shows `into_iter()` and a first `next()` call even
+/// for empty input. Wrapping the iterator here does the `len() == 0` match up
+/// front and stores `None` for empty input, so later `next()` calls return
+/// without touching the inner iterator. `Option<I>` is already stack-only, so
+/// no `MaybeUninit` is needed to stay allocation-free.
+///
+/// ```
+/// use async_loop_perf::ConditionalAsyncIterator;
+///
+/// let mut empty = ConditionalAsyncIterator::new(Vec::<i32>::new().into_iter());
+/// assert!(empty.is_empty());
+/// assert_eq!(empty.next(), None);
+///
+/// let mut items = ConditionalAsyncIterator::new(vec![1, 2].into_iter());
+/// assert!(!items.is_empty());
+/// assert_eq!(items.next(), Some(1));
+/// ```
+pub struct ConditionalAsyncIterator<I: Iterator>(Option<I>);
+
+impl<I: ExactSizeIterator> ConditionalAsyncIterator<I> {
+    pub fn new(iter: I) -> Self {
+        match iter.len() {
+            0 => Self(None),
+            _ => Self(Some(iter)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl<I: Iterator> Iterator for ConditionalAsyncIterator<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.0.as_mut()?.next()
+    }
+}
+
+/// Awaits `f(item)` for every item, returning immediately for empty input.
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_loop_perf::run_conditional;
+///
+/// run_conditional(vec![1, 2, 3], |item| async move {
+///     assert!(item > 0);
+/// })
+/// .await;
+/// # });
+/// ```
+pub async fn run_conditional<I, F, Fut>(iter: I, f: F)
+where
+    I: IntoIterator,
+    I::IntoIter: ExactSizeIterator,
+    F: Fn(I::Item) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let iter = ConditionalAsyncIterator::new(iter.into_iter());
+    if iter.is_empty() {
+        return;
+    }
+    for item in iter {
+        f(item).await;
+    }
+}
+
+/// What a loop driven by [`run_with_stats`] did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AsyncLoopStats {
+    pub iterations: u64,
+    /// Times a per-item future returned `Poll::Pending`.
+    pub suspensions: u64,
+    pub wall_ns: u64,
+}
+
+/// Awaits `f(item)` for every item and reports how often the loop suspended.
+///
+/// Each per-item future is polled through a wrapper that counts its
+/// `Poll::Pending` results. That is the number of times the loop gave the
+/// executor back control, which can differ from the number of wake-ups: a
+/// future may be woken spuriously, or wake itself before returning `Pending`.
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_loop_perf::run_with_stats;
+///
+/// let stats = run_with_stats(vec![1, 2, 3], |_| tokio::task::yield_now()).await;
+/// assert_eq!(stats.iterations, 3);
+/// assert_eq!(stats.suspensions, 3);
+/// # });
+/// ```
+pub async fn run_with_stats<I, F, Fut>(iter: I, mut f: F) -> AsyncLoopStats
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let start = Instant::now();
+    let mut stats = AsyncLoopStats::default();
+    for item in iter {
+        let mut fut = pin!(f(item));
+        poll_fn(|cx| {
+            let poll = fut.as_mut().poll(cx);
+            if poll.is_pending() {
+                stats.suspensions += 1;
+            }
+            poll
+        })
+        .await;
+        stats.iterations += 1;
+    }
+    stats.wall_ns = start.elapsed().as_nanos() as u64;
+    stats
+}
+
+/// A future that counts how many times it is polled.
+///
+/// A future that completes without suspending is polled exactly once; any
+/// extra polls are either real `Pending` round-trips or spurious re-polls by
+/// the executor. The count is shared, so it can still be read after the
+/// wrapper has been consumed by `.await`. `PollCounter<F>` is `Unpin`
+/// whenever `F` is.
+pub struct PollCounter<F: Future>(F, Arc<AtomicUsize>);
+
+impl<F: Future> Future for PollCounter<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: the inner future is never moved out of `self`, and
+        // `PollCounter` has no `Drop` impl that could move it.
+        unsafe { self.map_unchecked_mut(|counter| &mut counter.0) }.poll(cx)
+    }
+}
+
+/// Wraps `f` in a [`PollCounter`], returning it with a handle to the count.
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_loop_perf::count_polls;
+/// use std::sync::atomic::Ordering;
+///
+/// let (fut, polls) = count_polls(async {});
+/// fut.await;
+/// assert_eq!(polls.load(Ordering::Relaxed), 1);
+/// # });
+/// ```
+pub fn count_polls<F: Future>(f: F) -> (PollCounter<F>, Arc<AtomicUsize>) {
+    let polls = Arc::new(AtomicUsize::new(0));
+    (PollCounter(f, Arc::clone(&polls)), polls)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alloc_tracking::with_alloc_count;
+    use crate::mir_demo::{no_check, with_check};
+
+    #[test]
+    fn run_conditional_empty_input_does_not_allocate() {
+        let (poll, allocs, deallocs) = with_alloc_count(|| {
+            let fut = pin!(run_conditional(Vec::<i32>::new(), |item| async move {
+                std::hint::black_box(item);
+            }));
+            let mut cx = Context::from_waker(futures::task::noop_waker_ref());
+            fut.poll(&mut cx)
+        });
+        assert_eq!(poll, Poll::Ready(()));
+        assert_eq!(allocs, 0);
+        assert_eq!(deallocs, 0);
+    }
+
+    #[tokio::test]
+    async fn run_with_stats_counts_each_yield_as_a_suspension() {
+        let stats = run_with_stats(0..4u32, |item| async move {
+            for _ in 0..item {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await;
+        assert_eq!(stats.iterations, 4);
+        assert_eq!(stats.suspensions, 1 + 2 + 3);
+    }
+
+    #[tokio::test]
+    async fn with_check_on_empty_input_is_polled_once() {
+        let data = vec![];
+        let (fut, polls) = count_polls(with_check(&data));
+        fut.await;
+        assert_eq!(polls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn no_check_on_empty_input_is_polled_once() {
+        // Same poll count as with_check; the extra work (iterator setup and
+        // a `next()` returning `None`, see the MIR for Cases A/B) all happens
+        // inside that one poll.
+        let data = vec![];
+        let (fut, polls) = count_polls(no_check(&data));
+        fut.await;
+        assert_eq!(polls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn each_pending_result_costs_one_more_poll() {
+        let (fut, polls) = count_polls(async {
+            for _ in 0..3 {
+                tokio::task::yield_now().await;
+            }
+        });
+        fut.await;
+        assert_eq!(polls.load(Ordering::Relaxed), 4);
+    }
+}
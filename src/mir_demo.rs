@@ -1,4 +1,10 @@
-use std::future::Future;
+// MIR walkthroughs for the patterns measured in `benches/async_loops.rs`.
+// Dump with: rustup run nightly rustc --edition 2024 -Z unpretty=mir src/mir_demo.rs
+#![allow(clippy::ptr_arg)] // `&Vec<i32>` is the type under study, not a slice
+
+use std::collections::{HashMap, VecDeque};
+use std::future::{Future, poll_fn};
+use std::task::Poll;
 
 pub async fn async_work() {
     // Simulates an async operation
@@ -18,4 +24,436 @@ pub async fn no_check(data: &Vec<i32>) {
     }
 }
 
+pub async fn async_work_fallible(item: i32) -> Result<(), String> {
+    // Simulates an async operation that rejects negative input
+    if item < 0 {
+        return Err(format!("negative item: {item}"));
+    }
+    Ok(())
+}
+
+// Case C: with_check_result
+pub async fn with_check_result(data: &Vec<i32>) -> Result<(), String> {
+    if !data.is_empty() {
+        for &item in data {
+            async_work_fallible(item).await?;
+        }
+    }
+    Ok(())
+}
+
+// Case D: no_check_result
+pub async fn no_check_result(data: &Vec<i32>) -> Result<(), String> {
+    for &item in data {
+        async_work_fallible(item).await?;
+    }
+    Ok(())
+}
+
+/*
+MIR for Cases C/D: what `?` adds to the state machine
+
+no_check_result path (empty data):
+  bb0 -> bb1 (into_iter) -> bb3 (next) -> bb4 (match None) -> bb7 -> bb19 (return Ready(Ok))
+  Operations: iterator creation + next() + Option match + return
+
+no_check_result per-item path (after the awaitee returns Ready):
+  bb12 (match Poll) -> bb14 (drop awaitee) -> bb15 (Try::branch) -> bb16 (match ControlFlow)
+    Continue -> bb17 -> bb3 (next item)
+    Break    -> bb18 (FromResidual::from_residual) -> bb25 -> bb19 (return Ready(Err))
+  Operations: one extra call + discriminant switch per iteration, even on success
+
+with_check_result path (empty data):
+  bb0 -> bb1 (is_empty) -> bb2 (branch) -> bb20 (return Ready(Ok))
+  Operations: len check + branch + return
+
+The `?` desugaring lives inside the loop body, so the empty-check avoids it
+completely, along with the iterator setup already seen in Case B.
+*/
+
+// Case E: with_check_slice
+pub async fn with_check_slice(data: &[i32]) {
+    if !data.is_empty() {
+        async_work().await;
+    }
+}
+
+// Case F: no_check_slice
+pub async fn no_check_slice(data: &[i32]) {
+    for _ in data {
+        async_work().await;
+    }
+}
+
+/*
+MIR for Cases E/F: `&[i32]` vs `&Vec<i32>`
+
+with_check_slice path (empty data):
+  bb0 -> bb1 (<[i32]>::is_empty) -> bb2 (branch) -> bb3 -> bb14 (return Ready)
+  Same blocks as with_check; only the callee changes from Vec::is_empty.
+
+no_check_slice path (empty data):
+  bb0 -> bb1 (<&[i32]>::into_iter) -> bb3 (next) -> bb4 (match None) -> bb7 (return Ready)
+  Same blocks as no_check; both produce a `slice::Iter<'_, i32>`.
+
+Both `is_empty` calls read the same length field, so the empty-path savings
+come entirely from skipping iterator construction, not from ownership.
+Taking `&[i32]` instead of `Vec<i32>` only removes the caller's clone.
+*/
+
+// Case G: recursive_async_loop
+// A recursive async fn has an infinitely sized future unless the recursive
+// call is boxed, so every level costs one `Box::pin` allocation.
+pub async fn recursive_async_loop(mut data: Vec<i32>) {
+    let Some(item) = data.pop() else { return };
+    std::hint::black_box(item);
+    async_work().await;
+    // Base-case empty-check: don't box a call that would return immediately
+    if !data.is_empty() {
+        Box::pin(recursive_async_loop(data)).await;
+    }
+}
+
+// Case H: recursive_async_loop_no_check
+pub async fn recursive_async_loop_no_check(mut data: Vec<i32>) {
+    let Some(item) = data.pop() else { return };
+    std::hint::black_box(item);
+    async_work().await;
+    Box::pin(recursive_async_loop_no_check(data)).await;
+}
+
+/*
+MIR for Cases G/H: the boxed recursive call
+
+recursive_async_loop_no_check, after the last item (data now empty):
+  bb13 -> bb14 (construct child) -> bb15 (Box::pin) -> bb18 -> bb19 (poll child)
+    child: bb0 -> bb1 (Vec::pop) -> bb2 (match None) -> bb4 (drop Vec) -> return Ready
+  Operations: allocation + child state machine entry + pop + drops + dealloc
+
+recursive_async_loop, after the last item:
+  bb13 -> bb14 (Vec::is_empty) -> bb15 (branch) -> bb16 -> bb27 (return Ready)
+  Operations: len check + branch + return
+
+For N items the no-check version allocates N boxes, the checked one N - 1.
+*/
+
+// Case I: hash_map_with_check
+pub async fn hash_map_with_check(data: &HashMap<i32, i32>) {
+    if !data.is_empty() {
+        for _ in data {
+            async_work().await;
+        }
+    }
+}
+
+// Case J: hash_map_no_check
+pub async fn hash_map_no_check(data: &HashMap<i32, i32>) {
+    for _ in data {
+        async_work().await;
+    }
+}
+
+/*
+MIR for Cases I/J: `&HashMap<i32, i32>`
+
+hash_map_no_check path (empty map):
+  bb0 -> bb1 (<&HashMap as IntoIterator>::into_iter) -> bb2 (store hash_map::Iter)
+      -> bb3 (hash_map::Iter::next) -> bb4 (match None) -> bb7 (return Ready)
+  Operations: iterator creation + state storage + next() + Option match + return
+
+hash_map_with_check path (empty map):
+  bb0 -> bb1 (HashMap::is_empty) -> bb2 (branch) -> bb3 -> bb18 (return Ready)
+  Operations: len check + branch + return
+
+The block structure matches Case B; the difference is what the calls do.
+`is_empty` reads the item count, like `Vec::len`. `into_iter` builds a
+hashbrown raw iterator: control-byte pointer, bucket pointer, end pointer,
+item count, and a bitmask from eagerly loading the first control group, even
+for the empty singleton table. The iterator is also larger than a
+`slice::Iter`, so it takes more space in the saved state.
+*/
+
+// Case K: deque_with_check
+// A `VecDeque` is a ring buffer: its items occupy `buf[head..cap]` followed
+// by `buf[..tail]` once they wrap around, so it is viewed as two slices
+// (`as_slices`). Its iterator walks the first slice and then the second.
+pub async fn deque_with_check(data: &VecDeque<i32>) {
+    if !data.is_empty() {
+        for _ in data {
+            async_work().await;
+        }
+    }
+}
+
+// Case L: deque_no_check
+pub async fn deque_no_check(data: &VecDeque<i32>) {
+    for _ in data {
+        async_work().await;
+    }
+}
+
+/*
+MIR for Cases K/L: `&VecDeque<i32>`
+
+deque_no_check path (empty deque):
+  bb0 -> bb1 (<&VecDeque as IntoIterator>::into_iter) -> bb2 (store vec_deque::Iter)
+      -> bb3 (vec_deque::Iter::next) -> bb4 (match None) -> bb7 (return Ready)
+  Operations: iterator creation + state storage + next() + Option match + return
+
+deque_with_check path (empty deque):
+  bb0 -> bb1 (VecDeque::is_empty) -> bb2 (branch) -> bb3 -> bb18 (return Ready)
+  Operations: len check + branch + return
+
+Same block shape as Case B. `into_iter` goes through `as_slices`, which
+works out from `head`, `len` and the capacity where the buffer wraps and
+builds two `slice::Iter`s, so the iterator is twice the size of a Vec's and
+its `next` falls through to the second slice when the first runs out.
+*/
+
+// Case M: async_fn_loop
+// The compiler-generated state machine for a loop with no suspension point.
+pub async fn async_fn_loop(data: Vec<i32>) {
+    for item in data {
+        std::hint::black_box(item);
+    }
+}
+
+// Case N: poll_fn_loop
+// The same loop written as a poll function. The closure owns the iterator,
+// and the first poll runs it to completion. A step that can return `Pending`
+// would have to keep its in-flight future in the closure across polls, which
+// needs boxing or unsafe pinning, so this only fits loops whose per-item work
+// is synchronous or itself poll-based (`poll_recv` and friends).
+pub fn poll_fn_loop(data: Vec<i32>) -> impl Future<Output = ()> {
+    let mut items = data.into_iter();
+    poll_fn(move |_cx| {
+        for item in items.by_ref() {
+            std::hint::black_box(item);
+        }
+        Poll::Ready(())
+    })
+}
+
+/*
+MIR for Cases M/N: compiler-generated vs hand-written poll
+
+async_fn_loop path (empty data), first poll:
+  bb0 (switch on resume state) -> bb1 (Vec::into_iter) -> bb3 (next) -> bb4 (match None)
+      -> bb7 (drop IntoIter) -> bb8 (return Ready, state = Returned)
+  Operations: state switch + iterator creation + next() + Option match + drop + return
+
+poll_fn_loop:
+  poll_fn_loop(): bb0 (Vec::into_iter) -> bb1 (poll_fn)   [at construction]
+  closure (empty data): bb1 (<&mut IntoIter>::into_iter) -> bb3 (next) -> bb4 (match None)
+      -> bb7 (return Ready)
+  Operations: next() + Option match + return
+
+The hand-written version has no resume-state switch and no "returned"
+state to record, and it builds the iterator when the future is created
+rather than on first poll. The `IntoIter` is dropped with the future
+instead of inside the poll. A call-site `is_empty()` still skips all of it.
+*/
+
+// Case O: loop_with_panic
+// Test-only, as the panic is deliberate; dump its MIR with `--cfg test`.
+// `DropLog` guards make the order in which unwinding drops state visible.
+#[cfg(test)]
+thread_local! {
+    static DROPPED: std::cell::RefCell<Vec<&'static str>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+#[cfg(test)]
+struct DropLog(&'static str);
+
+#[cfg(test)]
+impl Drop for DropLog {
+    fn drop(&mut self) {
+        DROPPED.with(|dropped| dropped.borrow_mut().push(self.0));
+    }
+}
+
+#[cfg(test)]
+pub async fn may_panic(item: i32) {
+    let _in_flight = DropLog("may_panic");
+    if item < 0 {
+        panic!("negative item: {item}");
+    }
+}
+
+#[cfg(test)]
+pub async fn loop_with_panic(data: &Vec<i32>) {
+    let _loop = DropLog("loop_with_panic");
+    for &item in data {
+        may_panic(item).await;
+    }
+}
+
+// Case P: loop_with_panic_checked
+#[cfg(test)]
+pub async fn loop_with_panic_checked(data: &Vec<i32>) {
+    let _loop = DropLog("loop_with_panic_checked");
+    if !data.is_empty() {
+        for &item in data {
+            may_panic(item).await;
+        }
+    }
+}
+
+/*
+MIR for Cases O/P: the unwind path
+
+loop_with_panic, normal path per item:
+  bb3 (next) -> bb4 (match Some) -> bb6 (may_panic) -> bb8 (into_future) -> bb9 (store awaitee)
+      -> bb10 -> bb11 (poll) -> bb12 (match Ready) -> bb14 (drop awaitee) -> bb3
+  Unwind edges: bb10/bb11 -> bb16; every other call -> bb17
+
+loop_with_panic, unwind path (panic while polling `may_panic`):
+  bb11 (poll unwinds) -> bb16 (cleanup: drop awaitee) -> bb17 (cleanup: drop DropLog)
+      -> bb18 (state = Panicked, resume)
+  Operations: drop awaitee + drop guard + poison state + resume unwinding
+
+loop_with_panic_checked, unwind path:
+  bb14 (poll unwinds) -> bb20 (cleanup: drop awaitee) -> bb21 (cleanup: drop DropLog)
+      -> bb22 (state = Panicked, resume)
+  The is_empty call (bb1) unwinds straight to bb21.
+
+Both variants run the same cleanup chain. The `slice::Iter` over `&Vec`
+has no drop glue, so the iterator adds nothing to unwinding; only an
+owning iterator such as `vec::IntoIter` would get its own cleanup block.
+Once a panic has passed through, the state is set to "Panicked" and any
+later poll hits the "`async fn` resumed after panicking" assert.
+*/
+
+// Case Q: with_check_array
+// A fixed-size array's length is the type-level constant `N`.
+pub async fn with_check_array<const N: usize>(data: [i32; N]) {
+    if !data.is_empty() {
+        for _ in data {
+            async_work().await;
+        }
+    }
+}
+
+// Case R: no_check_array
+pub async fn no_check_array<const N: usize>(data: [i32; N]) {
+    for _ in data {
+        async_work().await;
+    }
+}
+
+/*
+MIR for Cases Q/R: fixed-size array (generic over N)
+
+with_check_array, first poll:
+  bb0 (switch on resume state) -> bb1 (unsize to &[i32], is_empty) -> bb2 (switch)
+      -> bb3 (N == 0: return Ready)
+      -> bb4 (<[i32; N]>::into_iter) -> bb6 (next) -> bb7 (match) -> ...
+  Operations: state switch + is_empty call + branch, then the loop as in R
+
+no_check_array, first poll:
+  bb0 (switch on resume state) -> bb1 (<[i32; N]>::into_iter) -> bb3 (next)
+      -> bb4 (match None) -> drop IntoIter -> return Ready
+  Operations: state switch + iterator creation + next() + Option match + drop + return
+
+`--emit=mir` (like `-Z unpretty=mir`) prints one polymorphic body per
+function, before monomorphization, so `N` is still a parameter and no
+block is eliminated for N = 0: the `is_empty` call and the loop are both
+present. Folding `N == 0` to a constant happens per instance in LLVM, after
+inlining `is_empty`; there the check becomes a compile-time branch. The
+guard is not free for N > 0, though: `data` has to stay alive across the
+check, so with_check moves the array into the state (bb1) and copies it
+again into the `IntoIter` (bb4), where no_check copies it once (bb1).
+*/
+
+// Case S: lifetime_demo
+// Two borrows with unrelated lifetimes, both held across an await.
+#[allow(clippy::needless_lifetimes)] // the named lifetimes are what the demo is about
+pub async fn lifetime_demo<'a, 'b>(a: &'a [i32], b: &'b [i32]) {
+    if !a.is_empty() {
+        for _ in a {
+            async_work().await;
+        }
+    }
+    if !b.is_empty() {
+        for _ in b {
+            async_work().await;
+        }
+    }
+}
+
+/*
+MIR for Case S: where the two borrows live in the state machine
+
+lifetime_demo (the constructor) only captures the arguments:
+  _0 = {coroutine} { a: copy _1, b: copy _2 }
+  Both references are upvar fields `.0` and `.1`, used while the future
+  is still in the Unresumed state (discriminant 0).
+
+lifetime_demo::{closure#0}, first poll:
+  bb1: _3 = copy (.0)                            (a moves to a local)
+       (variant#3).0: &[i32] = copy (.1)        (b moves into variant 3)
+  variant#3 (suspended in the first loop):  b, slice::Iter over a, awaitee
+  variant#4 (suspended in the second loop): slice::Iter over b, awaitee
+  `a` itself is not saved across the first await: only its iterator is,
+  and that iterator is what carries the `'a` borrow. `b` has to be saved,
+  because it is still needed after the first loop.
+
+There is no `_phantom` field, and no `'a: 'b` bound anywhere in the
+output. MIR prints with lifetimes erased (`&[i32]`, `Iter<'_, i32>`), and
+a coroutine needs no `PhantomData` because the references are real fields
+of its variants. Since `'a` and `'b` are unrelated parameters, nothing
+makes the compiler relate them either. What the lifetimes constrain is the
+future's type: it captures both, so it is only valid while both borrows
+are. It can be held in a struct or a `join!` that outlives neither, but it
+cannot be `tokio::spawn`ed (which needs `'static`) unless both inputs are
+`'static`. A bound like `'a: 'b` only shows up when the signature or a
+caller asks for it, e.g. by returning data borrowed from `a` as `&'b`.
+*/
+
+#[allow(dead_code)] // entry point for the standalone `rustc -Z unpretty=mir` dump
 fn main() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::pin;
+    use std::task::{Context, Waker};
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn dropped_while_panicking<F: Future<Output = ()>>(
+        fut: F,
+    ) -> (Vec<&'static str>, Box<dyn std::any::Any + Send>) {
+        DROPPED.with(|dropped| dropped.borrow_mut().clear());
+        let payload = panic::catch_unwind(AssertUnwindSafe(|| block_on(fut))).unwrap_err();
+        (DROPPED.with(|dropped| dropped.take()), payload)
+    }
+
+    #[test]
+    #[should_panic(expected = "negative item: -1")]
+    fn panic_inside_the_loop_drops_the_awaitee_before_the_loop_state() {
+        let data = vec![1, -1, 2];
+
+        let (dropped, _) = dropped_while_panicking(loop_with_panic_checked(&data));
+        assert_eq!(
+            dropped,
+            ["may_panic", "may_panic", "loop_with_panic_checked"]
+        );
+
+        let (dropped, payload) = dropped_while_panicking(loop_with_panic(&data));
+        // Item 1 completes and drops its guard; item -1 unwinds from inside
+        // `may_panic`, then the loop's own state is dropped.
+        assert_eq!(dropped, ["may_panic", "may_panic", "loop_with_panic"]);
+        panic::resume_unwind(payload);
+    }
+}
@@ -0,0 +1,110 @@
+//! Hand-rolled wakers for driving futures outside an executor.
+//!
+//! [`noop_waker`] does nothing; a [`CountingWaker`] records every clone, wake
+//! and drop made through the `Waker`s it hands out, which shows how often a
+//! future actually touches its waker. A future only interacts with the waker
+//! when it is about to return `Pending` (to arrange a wake-up), so a loop
+//! that completes on the first poll leaves every count at zero.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{RawWaker, RawWakerVTable, Waker};
+
+const NOOP_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |_| RawWaker::new(std::ptr::null(), &NOOP_VTABLE),
+    |_| {},
+    |_| {},
+    |_| {},
+);
+
+/// A waker whose clone, wake and drop do nothing.
+pub fn noop_waker() -> Waker {
+    // SAFETY: every vtable function ignores the (null) data pointer.
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &NOOP_VTABLE)) }
+}
+
+/// Interactions recorded by a [`CountingWaker`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WakerCounts {
+    pub clones: usize,
+    pub wakes: usize,
+    pub wakes_by_ref: usize,
+    pub drops: usize,
+}
+
+impl WakerCounts {
+    /// Every clone and wake; drops only mirror clones and wakes.
+    pub fn interactions(&self) -> usize {
+        self.clones + self.wakes + self.wakes_by_ref
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    clones: AtomicUsize,
+    wakes: AtomicUsize,
+    wakes_by_ref: AtomicUsize,
+    drops: AtomicUsize,
+}
+
+/// Hands out `Waker`s that count what is done with them.
+#[derive(Default)]
+pub struct CountingWaker {
+    counters: Arc<Counters>,
+}
+
+const COUNTING_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    counting_clone,
+    counting_wake,
+    counting_wake_by_ref,
+    counting_drop,
+);
+
+// SAFETY (all four): `data` comes from `Arc::into_raw` on an `Arc<Counters>`
+// and each live `RawWaker` owns one strong count.
+
+unsafe fn counting_clone(data: *const ()) -> RawWaker {
+    let counters = unsafe { &*data.cast::<Counters>() };
+    counters.clones.fetch_add(1, Ordering::Relaxed);
+    unsafe { Arc::increment_strong_count(data.cast::<Counters>()) };
+    RawWaker::new(data, &COUNTING_VTABLE)
+}
+
+unsafe fn counting_wake(data: *const ()) {
+    let counters = unsafe { Arc::from_raw(data.cast::<Counters>()) };
+    counters.wakes.fetch_add(1, Ordering::Relaxed);
+    counters.drops.fetch_add(1, Ordering::Relaxed);
+}
+
+unsafe fn counting_wake_by_ref(data: *const ()) {
+    let counters = unsafe { &*data.cast::<Counters>() };
+    counters.wakes_by_ref.fetch_add(1, Ordering::Relaxed);
+}
+
+unsafe fn counting_drop(data: *const ()) {
+    let counters = unsafe { Arc::from_raw(data.cast::<Counters>()) };
+    counters.drops.fetch_add(1, Ordering::Relaxed);
+}
+
+impl CountingWaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new `Waker` reporting to this counter. Creating it is not counted.
+    pub fn waker(&self) -> Waker {
+        let data = Arc::into_raw(self.counters.clone()).cast::<()>();
+        // SAFETY: `data` owns one strong count, as the vtable expects.
+        unsafe { Waker::from_raw(RawWaker::new(data, &COUNTING_VTABLE)) }
+    }
+
+    pub fn counts(&self) -> WakerCounts {
+        let c = &self.counters;
+        WakerCounts {
+            clones: c.clones.load(Ordering::Relaxed),
+            wakes: c.wakes.load(Ordering::Relaxed),
+            wakes_by_ref: c.wakes_by_ref.load(Ordering::Relaxed),
+            drops: c.drops.load(Ordering::Relaxed),
+        }
+    }
+}